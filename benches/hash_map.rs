@@ -0,0 +1,74 @@
+// compares the HashMap probing strategies on a few workloads; run with `cargo bench`
+//
+// hit:   looking up keys that are all present
+// miss:  looking up keys that are all absent
+// churn: removing one key and inserting a fresh one, keeping the size constant
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use rust_data_structures::hash_map::{HashMap, Strategy};
+
+const SIZES: [u64; 3] = [1_000, 100_000, 1_000_000];
+const STRATEGIES: [Strategy; 3] = [Strategy::Linear, Strategy::RobinHood, Strategy::Swiss];
+// each measurement is repeated and the fastest run kept, to filter out scheduling noise
+const RUNS: usize = 5;
+
+type Workload = fn(&mut HashMap<u64, u64>, u64) -> Duration;
+
+fn filled(strategy: Strategy, size: u64) -> HashMap<u64, u64> {
+    let mut map = HashMap::with_strategy(strategy);
+    map.reserve(size as usize);
+    for key in 0..size {
+        map.insert(key, key);
+    }
+    map
+}
+
+fn hit(map: &mut HashMap<u64, u64>, size: u64) -> Duration {
+    let start = Instant::now();
+    for key in 0..size {
+        black_box(map.get(&black_box(key)));
+    }
+    start.elapsed()
+}
+
+fn miss(map: &mut HashMap<u64, u64>, size: u64) -> Duration {
+    let start = Instant::now();
+    for key in size..size * 2 {
+        black_box(map.get(&black_box(key)));
+    }
+    start.elapsed()
+}
+
+fn churn(map: &mut HashMap<u64, u64>, size: u64) -> Duration {
+    let start = Instant::now();
+    for key in 0..size {
+        black_box(map.remove(&key));
+        black_box(map.insert(key + size, key));
+    }
+    start.elapsed()
+}
+
+fn measure(strategy: Strategy, size: u64, workload: Workload) -> f64 {
+    let best = (0..RUNS)
+        .map(|_| workload(&mut filled(strategy, size), size))
+        .min()
+        .unwrap();
+    best.as_nanos() as f64 / size as f64
+}
+
+fn main() {
+    let workloads: [(&str, Workload); 3] = [("hit", hit), ("miss", miss), ("churn", churn)];
+
+    println!("{:<8} {:>10} {:>12} {:>12} {:>12}", "workload", "size", "Linear", "RobinHood", "Swiss");
+    for (name, workload) in workloads {
+        for size in SIZES {
+            print!("{:<8} {:>10}", name, size);
+            for strategy in STRATEGIES {
+                print!(" {:>9.1} ns", measure(strategy, size, workload));
+            }
+            println!();
+        }
+    }
+}
@@ -0,0 +1,846 @@
+use std::cmp::Ordering;
+use std::ops::RangeBounds;
+
+use crate::binary_search_tree::{
+    self, build_balanced, subtree_size, Difference, Intersection, Iter, IntoIter, LevelOrderIter, Node,
+    PostOrderIter, PreOrderIter, Range, SymmetricDifference, Union,
+};
+use crate::compare::{Compare, Natural};
+
+// same node layout as BinarySearchTree, with each node's height stored in `meta`
+// (a leaf has height 1, an empty subtree height 0)
+type Link<T> = Option<Box<Node<T, usize>>>;
+
+// values are ordered by the comparator `C`, which defaults to the type's own Ord, and the
+// API matches BinarySearchTree's so either tree can stand in for the other
+pub struct AvlTree<T, C = Natural> {
+    root: Link<T>,
+    size: usize,
+    cmp: C,
+}
+
+impl<T> AvlTree<T> {
+    pub fn new() -> Self {
+        Self::with_comparator(Natural)
+    }
+}
+
+impl<T: Ord> AvlTree<T> {
+    pub fn from_sorted_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+    {
+        Self::from_sorted_iter_by(iter, Natural)
+    }
+}
+
+impl<T, C> AvlTree<T, C> {
+    pub fn with_comparator(cmp: C) -> Self {
+        AvlTree {
+            root: None,
+            size: 0,
+            cmp,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn clear(&mut self) {
+        binary_search_tree::tear_down(self.root.take());
+        self.size = 0;
+    }
+
+    pub fn height(&self) -> usize {
+        height(&self.root)
+    }
+
+    pub fn iter(&self) -> Iter<'_, T, usize> {
+        Iter::new(self.root.as_deref(), self.size)
+    }
+
+    pub fn pre_order_iter(&self) -> PreOrderIter<'_, T, usize> {
+        PreOrderIter::new(self.root.as_deref())
+    }
+
+    pub fn post_order_iter(&self) -> PostOrderIter<'_, T, usize> {
+        PostOrderIter::new(self.root.as_deref())
+    }
+
+    pub fn level_order_iter(&self) -> LevelOrderIter<'_, T, usize> {
+        LevelOrderIter::new(self.root.as_deref())
+    }
+
+    // replaces the (empty) tree's contents with `values`, which must be strictly increasing.
+    // a perfectly balanced subtree of n nodes is as tall as n has bits
+    fn rebuild(&mut self, values: Vec<T>) {
+        self.size = values.len();
+        let height = |count: usize| (usize::BITS - count.leading_zeros()) as usize;
+        self.root = build_balanced(&mut values.into_iter(), self.size, &height);
+    }
+
+    // empties the tree, handing back its values in order
+    fn drain(&mut self) -> IntoIter<T, usize> {
+        IntoIter::new(self.root.take(), std::mem::take(&mut self.size))
+    }
+}
+
+impl<T, C: Compare<T>> AvlTree<T, C> {
+    pub fn insert(&mut self, value: T) -> bool {
+        let inserted = Self::insert_at(&mut self.root, value, &self.cmp);
+        if inserted {
+            self.size += 1;
+        }
+        inserted
+    }
+
+    pub fn contains(&self, value: &T) -> bool {
+        binary_search_tree::find(self.root.as_deref(), value, &self.cmp).is_some()
+    }
+
+    pub fn remove(&mut self, value: &T) -> Option<T> {
+        let removed = Self::remove_at(&mut self.root, value, &self.cmp);
+        if removed.is_some() {
+            self.size -= 1;
+        }
+        removed
+    }
+
+    pub fn from_sorted_iter_by<I>(iter: I, cmp: C) -> Self
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let mut values: Vec<T> = iter.into_iter().collect();
+        // the O(n) build relies on the order, so fall back to sorting anything that isn't
+        if !values.is_sorted_by(|a, b| cmp.compare(a, b) != Ordering::Greater) {
+            values.sort_by(|a, b| cmp.compare(a, b));
+        }
+        values.dedup_by(|a, b| cmp.compare(a, b) == Ordering::Equal);
+
+        let mut tree = Self::with_comparator(cmp);
+        tree.rebuild(values);
+        tree
+    }
+
+    // splits off every value greater than or equal to `value`, returning them as a new tree;
+    // both halves are rebuilt perfectly balanced in O(n)
+    pub fn split_off(&mut self, value: &T) -> Self
+    where
+        C: Clone,
+    {
+        let mut lower: Vec<T> = self.drain().collect();
+        let split = lower.partition_point(|current| self.cmp.compare(current, value) == Ordering::Less);
+        let upper = lower.split_off(split);
+        self.rebuild(lower);
+
+        let mut tree = Self::with_comparator(self.cmp.clone());
+        tree.rebuild(upper);
+        tree
+    }
+
+    // moves every value out of `other` into this tree, leaving `other` empty; values present in
+    // both trees are kept once. The merged tree is rebuilt perfectly balanced in O(n + m)
+    pub fn append(&mut self, other: &mut Self) {
+        if other.is_empty() {
+            return;
+        }
+
+        let merged = binary_search_tree::merge_sorted(self.drain(), other.drain(), &self.cmp);
+        self.rebuild(merged);
+    }
+
+    pub fn union<'a>(&'a self, other: &'a Self) -> Union<'a, T, C, usize> {
+        Union::new(self.iter(), other.iter(), &self.cmp)
+    }
+
+    pub fn intersection<'a>(&'a self, other: &'a Self) -> Intersection<'a, T, C, usize> {
+        Intersection::new(self.iter(), other.iter(), &self.cmp)
+    }
+
+    pub fn difference<'a>(&'a self, other: &'a Self) -> Difference<'a, T, C, usize> {
+        Difference::new(self.iter(), other.iter(), &self.cmp)
+    }
+
+    pub fn symmetric_difference<'a>(&'a self, other: &'a Self) -> SymmetricDifference<'a, T, C, usize> {
+        SymmetricDifference::new(self.iter(), other.iter(), &self.cmp)
+    }
+
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.size <= other.size && binary_search_tree::is_subset(self.iter(), other.iter(), &self.cmp)
+    }
+
+    pub fn is_superset(&self, other: &Self) -> bool {
+        other.is_subset(self)
+    }
+
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+        self.intersection(other).next().is_none()
+    }
+
+    pub fn entry(&mut self, value: T) -> Entry<'_, T> {
+        // the links on the way down are kept as raw pointers so the entry can rebalance every
+        // ancestor, bottom up, after inserting or removing
+        let mut path = Vec::new();
+        let mut slot: *mut Link<T> = &mut self.root;
+
+        // SAFETY: `slot` always points at self.root or at a child link of a node reached from
+        // it, and nothing else touches the tree while the walk runs
+        unsafe {
+            while let Some(node) = (*slot).as_deref_mut() {
+                let ordering = self.cmp.compare(&value, &node.value);
+                if ordering == Ordering::Equal {
+                    return Entry::Occupied(OccupiedEntry {
+                        value,
+                        slot,
+                        path,
+                        size: &mut self.size,
+                    });
+                }
+
+                path.push(slot);
+                slot = if ordering == Ordering::Less { &mut node.left } else { &mut node.right };
+            }
+        }
+
+        Entry::Vacant(VacantEntry {
+            value,
+            slot,
+            path,
+            size: &mut self.size,
+        })
+    }
+
+    pub fn min(&self) -> Option<&T> {
+        binary_search_tree::min(self.root.as_deref())
+    }
+
+    pub fn max(&self) -> Option<&T> {
+        binary_search_tree::max(self.root.as_deref())
+    }
+
+    pub fn floor(&self, value: &T) -> Option<&T> {
+        binary_search_tree::bound_below(self.root.as_deref(), value, true, &self.cmp)
+    }
+
+    pub fn ceiling(&self, value: &T) -> Option<&T> {
+        binary_search_tree::bound_above(self.root.as_deref(), value, true, &self.cmp)
+    }
+
+    pub fn predecessor(&self, value: &T) -> Option<&T> {
+        binary_search_tree::bound_below(self.root.as_deref(), value, false, &self.cmp)
+    }
+
+    pub fn successor(&self, value: &T) -> Option<&T> {
+        binary_search_tree::bound_above(self.root.as_deref(), value, false, &self.cmp)
+    }
+
+    pub fn select(&self, k: usize) -> Option<&T> {
+        binary_search_tree::select(self.root.as_deref(), k)
+    }
+
+    pub fn rank(&self, value: &T) -> usize {
+        binary_search_tree::count_below(self.root.as_deref(), value, false, &self.cmp)
+    }
+
+    pub fn count_in_range<R>(&self, range: R) -> usize
+    where
+        R: RangeBounds<T>,
+    {
+        binary_search_tree::count_in_range(self.root.as_deref(), range, &self.cmp)
+    }
+
+    pub fn range<R>(&self, range: R) -> Range<'_, T, usize>
+    where
+        R: RangeBounds<T>,
+    {
+        Range::new(self.root.as_deref(), range, &self.cmp)
+    }
+
+    pub fn in_order<F>(&self, visit: F)
+    where
+        F: FnMut(&T),
+    {
+        self.iter().for_each(visit);
+    }
+
+    pub fn pre_order<F>(&self, visit: F)
+    where
+        F: FnMut(&T),
+    {
+        self.pre_order_iter().for_each(visit);
+    }
+
+    pub fn post_order<F>(&self, visit: F)
+    where
+        F: FnMut(&T),
+    {
+        self.post_order_iter().for_each(visit);
+    }
+
+    pub fn level_order<F>(&self, visit: F)
+    where
+        F: FnMut(&T),
+    {
+        self.level_order_iter().for_each(visit);
+    }
+
+    // recursion is fine here: the balance invariant keeps the depth around 1.44 * log2(n)
+    fn insert_at(link: &mut Link<T>, value: T, cmp: &C) -> bool {
+        let Some(node) = link else {
+            *link = Some(Box::new(Node::new(value, 1)));
+            return true;
+        };
+
+        let inserted = match cmp.compare(&value, &node.value) {
+            Ordering::Less => Self::insert_at(&mut node.left, value, cmp),
+            Ordering::Greater => Self::insert_at(&mut node.right, value, cmp),
+            Ordering::Equal => false,
+        };
+
+        if inserted {
+            rebalance_at(link);
+        }
+        inserted
+    }
+
+    fn remove_at(link: &mut Link<T>, value: &T, cmp: &C) -> Option<T> {
+        let node = link.as_mut()?;
+
+        let removed = match cmp.compare(value, &node.value) {
+            Ordering::Less => Self::remove_at(&mut node.left, value, cmp),
+            Ordering::Greater => Self::remove_at(&mut node.right, value, cmp),
+            Ordering::Equal => return Some(unlink(link)),
+        };
+
+        if removed.is_some() {
+            rebalance_at(link);
+        }
+        removed
+    }
+}
+
+// detaches the node in `link` and splices its children back in; the caller rebalances the
+// link's ancestors
+fn unlink<T>(link: &mut Link<T>) -> T {
+    let mut node = link.take().unwrap();
+    *link = match (node.left.take(), node.right.take()) {
+        (None, None) => None,
+        (Some(left), None) => Some(left),
+        (None, Some(right)) => Some(right),
+        (Some(left), Some(right)) => {
+            // two children: the in-order successor takes this node's place
+            let (mut successor, rest) = take_min(right);
+            successor.left = Some(left);
+            successor.right = rest;
+            Some(rebalance(successor))
+        }
+    };
+    node.value
+}
+
+fn height<T>(link: &Link<T>) -> usize {
+    link.as_ref().map_or(0, |node| node.meta)
+}
+
+fn update<T>(node: &mut Node<T, usize>) {
+    node.meta = 1 + height(&node.left).max(height(&node.right));
+    node.size = 1 + subtree_size(&node.left) + subtree_size(&node.right);
+}
+
+fn balance_factor<T>(node: &Node<T, usize>) -> isize {
+    height(&node.left) as isize - height(&node.right) as isize
+}
+
+fn rotate_right<T>(mut node: Box<Node<T, usize>>) -> Box<Node<T, usize>> {
+    let mut left = node.left.take().unwrap();
+    node.left = left.right.take();
+    update(&mut node);
+    left.right = Some(node);
+    update(&mut left);
+    left
+}
+
+fn rotate_left<T>(mut node: Box<Node<T, usize>>) -> Box<Node<T, usize>> {
+    let mut right = node.right.take().unwrap();
+    node.right = right.left.take();
+    update(&mut node);
+    right.left = Some(node);
+    update(&mut right);
+    right
+}
+
+fn rebalance<T>(mut node: Box<Node<T, usize>>) -> Box<Node<T, usize>> {
+    update(&mut node);
+
+    match balance_factor(&node) {
+        2.. => {
+            // left-right case: straighten the zig-zag before the single rotation
+            if node.left.as_deref().is_some_and(|left| balance_factor(left) < 0) {
+                node.left = node.left.take().map(rotate_left);
+            }
+            rotate_right(node)
+        }
+        ..=-2 => {
+            if node.right.as_deref().is_some_and(|right| balance_factor(right) > 0) {
+                node.right = node.right.take().map(rotate_right);
+            }
+            rotate_left(node)
+        }
+        _ => node,
+    }
+}
+
+fn rebalance_at<T>(link: &mut Link<T>) {
+    *link = link.take().map(rebalance);
+}
+
+// detaches the smallest node of the subtree, returning it along with what's left of the subtree
+fn take_min<T>(mut node: Box<Node<T, usize>>) -> (Box<Node<T, usize>>, Link<T>) {
+    match node.left.take() {
+        None => {
+            let rest = node.right.take();
+            (node, rest)
+        }
+        Some(left) => {
+            let (min, rest) = take_min(left);
+            node.left = rest;
+            (min, Some(rebalance(node)))
+        }
+    }
+}
+
+impl<T, C: Default> Default for AvlTree<T, C> {
+    fn default() -> Self {
+        Self::with_comparator(C::default())
+    }
+}
+
+impl<'a, T, C> IntoIterator for &'a AvlTree<T, C> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T, usize>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T, C> IntoIterator for AvlTree<T, C> {
+    type Item = T;
+    type IntoIter = IntoIter<T, usize>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter::new(self.root, self.size)
+    }
+}
+
+// the same entry API as BinarySearchTree's, and for the same reason without or_insert_with or
+// and_modify: the value is its own key, so changing it in place could move it out of order.
+// OccupiedEntry::replace swaps in the entry's equal value instead
+pub enum Entry<'a, T> {
+    Occupied(OccupiedEntry<'a, T>),
+    Vacant(VacantEntry<'a, T>),
+}
+
+// both entry kinds point into the tree they were created from: `slot` is the link that holds
+// (or would hold) the value and `path` lists the links above it, root first. the borrow of the
+// tree's size keeps the tree itself mutably borrowed for as long as the entry lives, so no
+// node can be moved or freed behind the raw pointers. rebalancing a link only rearranges the
+// nodes below it, which is why the path is fixed up from the bottom
+pub struct OccupiedEntry<'a, T> {
+    value: T, // the value the entry was looked up with
+    slot: *mut Link<T>,
+    path: Vec<*mut Link<T>>,
+    size: &'a mut usize,
+}
+
+pub struct VacantEntry<'a, T> {
+    value: T,
+    slot: *mut Link<T>,
+    path: Vec<*mut Link<T>>,
+    size: &'a mut usize,
+}
+
+impl<'a, T> Entry<'a, T> {
+    pub fn get(&self) -> &T {
+        match self {
+            Entry::Occupied(entry) => entry.get(),
+            Entry::Vacant(entry) => entry.get(),
+        }
+    }
+
+    pub fn or_insert(self) -> &'a T {
+        match self {
+            Entry::Occupied(entry) => entry.into_ref(),
+            Entry::Vacant(entry) => entry.insert(),
+        }
+    }
+}
+
+impl<'a, T> OccupiedEntry<'a, T> {
+    pub fn get(&self) -> &T {
+        // SAFETY: the slot is occupied and the tree stays borrowed through `size` (see above)
+        unsafe { &(*self.slot).as_ref().unwrap().value }
+    }
+
+    pub fn into_ref(self) -> &'a T {
+        // SAFETY: as in get; the tree borrow lasts for 'a, so the reference can too
+        unsafe { &(*self.slot).as_ref().unwrap().value }
+    }
+
+    // stores the entry's value in place of the equal one already in the tree, and returns
+    // the old one
+    pub fn replace(self) -> T {
+        // SAFETY: as in get
+        let node = unsafe { (*self.slot).as_mut().unwrap() };
+        std::mem::replace(&mut node.value, self.value)
+    }
+
+    pub fn remove_entry(self) -> T {
+        // SAFETY: the tree stays borrowed through `size` (see above), and each rebalance only
+        // touches nodes below the link being fixed, so the links higher up stay where they are
+        unsafe {
+            let value = unlink(&mut *self.slot);
+            for &link in self.path.iter().rev() {
+                rebalance_at(&mut *link);
+            }
+            *self.size -= 1;
+            value
+        }
+    }
+}
+
+impl<'a, T> VacantEntry<'a, T> {
+    pub fn get(&self) -> &T {
+        &self.value
+    }
+
+    pub fn into_value(self) -> T {
+        self.value
+    }
+
+    pub fn insert(self) -> &'a T {
+        // SAFETY: as in OccupiedEntry::remove_entry. rotations move boxes between links but
+        // never the boxed nodes themselves, so the new value stays where it was put
+        unsafe {
+            *self.slot = Some(Box::new(Node::new(self.value, 1)));
+            let value: *const T = &(*self.slot).as_ref().unwrap().value;
+            for &link in self.path.iter().rev() {
+                rebalance_at(&mut *link);
+            }
+            *self.size += 1;
+            &*value
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compare::Reversed;
+
+    fn create_test_tree(values: &[i32]) -> AvlTree<i32> {
+        let mut tree = AvlTree::new();
+        for &value in values {
+            tree.insert(value);
+        }
+        tree
+    }
+
+    fn collect_in_order(tree: &AvlTree<i32>) -> Vec<i32> {
+        tree.iter().copied().collect()
+    }
+
+    // the tallest AVL tree with n nodes is bounded by 1.44 * log2(n + 2)
+    fn max_avl_height(size: usize) -> usize {
+        (1.4405 * ((size + 2) as f64).log2() - 0.3277).floor() as usize
+    }
+
+    // checks ordering, stored heights and sizes, and the balance factor of every node; returns the height
+    fn assert_invariants(link: &Link<i32>, lower: Option<i32>, upper: Option<i32>) -> usize {
+        let Some(node) = link else {
+            return 0;
+        };
+
+        assert!(lower.is_none_or(|lower| node.value > lower));
+        assert!(upper.is_none_or(|upper| node.value < upper));
+
+        let left = assert_invariants(&node.left, lower, Some(node.value));
+        let right = assert_invariants(&node.right, Some(node.value), upper);
+        assert!(left.abs_diff(right) <= 1, "unbalanced at {}", node.value);
+        assert_eq!(node.meta, 1 + left.max(right), "stale height at {}", node.value);
+        let size = subtree_size(&node.left) + subtree_size(&node.right) + 1;
+        assert_eq!(node.size, size, "stale size at {}", node.value);
+        node.meta
+    }
+
+    fn assert_balanced(tree: &AvlTree<i32>) {
+        let height = assert_invariants(&tree.root, None, None);
+        assert_eq!(height, tree.height());
+        assert!(tree.height() <= max_avl_height(tree.size()));
+        assert_eq!(tree.iter().count(), tree.size());
+    }
+
+    #[test]
+    fn test_new_is_empty() {
+        let tree: AvlTree<i32> = AvlTree::new();
+        assert!(tree.is_empty());
+        assert_eq!(tree.size(), 0);
+        assert_eq!(tree.height(), 0);
+        assert_eq!(tree.min(), None);
+        assert_eq!(tree.max(), None);
+    }
+
+    #[test]
+    fn test_insert_and_contains() {
+        let mut tree = AvlTree::new();
+        assert!(tree.insert(10));
+        assert!(tree.insert(5));
+        assert!(tree.insert(15));
+        assert!(!tree.insert(10));
+
+        assert_eq!(tree.size(), 3);
+        assert!(tree.contains(&5));
+        assert!(!tree.contains(&7));
+        assert_eq!(collect_in_order(&tree), vec![5, 10, 15]);
+    }
+
+    #[test]
+    fn test_rotations() {
+        // each sequence triggers one of the four rebalancing cases at the root
+        for values in [[3, 2, 1], [1, 2, 3], [3, 1, 2], [1, 3, 2]] {
+            let tree = create_test_tree(&values);
+            assert_eq!(tree.height(), 2);
+            assert_eq!(tree.level_order_iter().copied().collect::<Vec<_>>(), vec![2, 1, 3]);
+            assert_balanced(&tree);
+        }
+    }
+
+    #[test]
+    fn test_sorted_insert_stays_balanced() {
+        let mut tree = AvlTree::new();
+        for i in 0..10_000 {
+            tree.insert(i);
+        }
+        assert_balanced(&tree);
+        assert!(tree.height() <= 14);
+
+        let mut tree = AvlTree::new();
+        for i in (0..10_000).rev() {
+            tree.insert(i);
+        }
+        assert_balanced(&tree);
+        assert_eq!(tree.min(), Some(&0));
+        assert_eq!(tree.max(), Some(&9_999));
+    }
+
+    #[test]
+    fn test_adversarial_insert_stays_balanced() {
+        // zig-zag towards the middle, which keeps forcing double rotations
+        let mut tree = AvlTree::new();
+        let (mut low, mut high) = (0, 4_095);
+        while low < high {
+            tree.insert(low);
+            tree.insert(high);
+            low += 1;
+            high -= 1;
+        }
+        assert_balanced(&tree);
+
+        // a pseudo-random permutation
+        let mut tree = AvlTree::new();
+        for i in 0..5_000 {
+            tree.insert((i * 7_919) % 5_003);
+        }
+        assert_balanced(&tree);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut tree = create_test_tree(&[50, 30, 70, 20, 40, 60, 80, 65]);
+
+        assert_eq!(tree.remove(&20), Some(20));
+        assert_eq!(tree.remove(&70), Some(70));
+        assert_eq!(tree.remove(&50), Some(50));
+        assert_eq!(tree.remove(&50), None);
+
+        assert_eq!(tree.size(), 5);
+        assert_eq!(collect_in_order(&tree), vec![30, 40, 60, 65, 80]);
+        assert_balanced(&tree);
+    }
+
+    #[test]
+    fn test_remove_stays_balanced() {
+        let mut tree = AvlTree::new();
+        for i in 0..2_000 {
+            tree.insert(i);
+        }
+
+        // strip one side of the tree entirely so the removals keep forcing rotations
+        for i in 0..1_500 {
+            assert_eq!(tree.remove(&i), Some(i));
+            if i % 100 == 0 {
+                assert_balanced(&tree);
+            }
+        }
+        assert_balanced(&tree);
+        assert_eq!(tree.size(), 500);
+        assert_eq!(tree.min(), Some(&1_500));
+
+        for i in 1_500..2_000 {
+            tree.remove(&i);
+        }
+        assert!(tree.is_empty());
+        assert_eq!(tree.height(), 0);
+    }
+
+    #[test]
+    fn test_matches_binary_search_tree_api() {
+        let mut avl = AvlTree::new();
+        let mut bst = crate::BinarySearchTree::new();
+        for value in [50, 30, 70, 20, 40, 60, 80, 35, 45, 65] {
+            assert_eq!(avl.insert(value), bst.insert(value));
+        }
+        assert_eq!(avl.remove(&30), bst.remove(&30));
+
+        assert!(avl.iter().eq(bst.iter()));
+        assert!(avl.range(33..66).eq(bst.range(33..66)));
+        assert_eq!(avl.floor(&64), bst.floor(&64));
+        assert_eq!(avl.ceiling(&64), bst.ceiling(&64));
+        assert_eq!(avl.predecessor(&40), bst.predecessor(&40));
+        assert_eq!(avl.successor(&40), bst.successor(&40));
+        assert_eq!(avl.size(), bst.size());
+    }
+
+    #[test]
+    fn test_matches_binary_search_tree_bulk_and_set_operations() {
+        let mut avl = AvlTree::from_sorted_iter(0..40);
+        let mut bst = crate::BinarySearchTree::from_sorted_iter(0..40);
+        assert_balanced(&avl);
+        assert!(avl.iter().eq(bst.iter()));
+
+        let mut avl_upper = avl.split_off(&25);
+        let mut bst_upper = bst.split_off(&25);
+        assert_balanced(&avl);
+        assert_balanced(&avl_upper);
+        assert!(avl.iter().eq(bst.iter()));
+        assert!(avl_upper.iter().eq(bst_upper.iter()));
+
+        let avl_other = AvlTree::from_sorted_iter((10..60).step_by(3));
+        let bst_other = crate::BinarySearchTree::from_sorted_iter((10..60).step_by(3));
+        assert!(avl.union(&avl_other).eq(bst.union(&bst_other)));
+        assert!(avl.intersection(&avl_other).eq(bst.intersection(&bst_other)));
+        assert!(avl.difference(&avl_other).eq(bst.difference(&bst_other)));
+        assert!(avl.symmetric_difference(&avl_other).eq(bst.symmetric_difference(&bst_other)));
+        assert_eq!(avl.is_subset(&avl_other), bst.is_subset(&bst_other));
+        assert_eq!(avl.is_disjoint(&avl_upper), bst.is_disjoint(&bst_upper));
+
+        avl.append(&mut avl_upper);
+        bst.append(&mut bst_upper);
+        assert_balanced(&avl);
+        assert!(avl_upper.is_empty());
+        assert!(avl.iter().eq(bst.iter()));
+        assert!(avl.is_superset(&AvlTree::from_sorted_iter([0, 25, 39])));
+    }
+
+    #[test]
+    fn test_entry() {
+        let mut avl = AvlTree::new();
+        let mut bst = crate::BinarySearchTree::new();
+        for value in (0..100).rev() {
+            assert_eq!(avl.entry(value).or_insert(), bst.entry(value).or_insert());
+        }
+        assert_balanced(&avl);
+        assert_eq!(avl.size(), 100);
+
+        for value in (0..100).step_by(3) {
+            match (avl.entry(value), bst.entry(value)) {
+                (Entry::Occupied(a), crate::binary_search_tree::Entry::Occupied(b)) => {
+                    assert_eq!(a.remove_entry(), b.remove_entry())
+                }
+                _ => panic!("{value} should be in both trees"),
+            }
+        }
+        assert_balanced(&avl);
+        assert!(avl.iter().eq(bst.iter()));
+
+        let Entry::Vacant(entry) = avl.entry(3) else {
+            panic!("3 was removed");
+        };
+        assert_eq!(entry.into_value(), 3);
+        assert_eq!(avl.size(), bst.size());
+    }
+
+    #[test]
+    fn test_with_comparator() {
+        let mut tree = AvlTree::with_comparator(Reversed(Natural));
+        for value in 0..50 {
+            tree.insert(value);
+        }
+        assert!(tree.height() <= max_avl_height(tree.size()));
+        assert_eq!(tree.min(), Some(&49));
+        assert_eq!(tree.floor(&10), Some(&10));
+        assert_eq!(tree.successor(&10), Some(&9));
+
+        let upper = tree.split_off(&10);
+        assert!(tree.iter().copied().eq((11..50).rev()));
+        assert!(upper.iter().copied().eq((0..=10).rev()));
+
+        let sorted = AvlTree::from_sorted_iter_by((0..10).rev(), Reversed(Natural));
+        assert!(sorted.iter().eq(upper.iter().skip(1)));
+        let default: AvlTree<i32, Reversed> = AvlTree::default();
+        assert!(default.is_empty());
+    }
+
+    #[test]
+    fn test_order_statistics() {
+        let mut tree = AvlTree::new();
+        for i in 0..1_000 {
+            tree.insert(i * 2);
+        }
+        for i in 0..250 {
+            tree.remove(&(i * 8));
+        }
+        assert_balanced(&tree);
+
+        let values: Vec<_> = tree.iter().copied().collect();
+        for (k, value) in values.iter().enumerate() {
+            assert_eq!(tree.select(k), Some(value));
+            assert_eq!(tree.rank(value), k);
+        }
+        assert_eq!(tree.select(values.len()), None);
+        assert_eq!(tree.count_in_range(0..100), 37);
+        assert_eq!(tree.count_in_range(..), 750);
+    }
+
+    #[test]
+    fn test_traversals() {
+        let tree = create_test_tree(&[4, 2, 6, 1, 3, 5, 7]);
+
+        let mut visited = Vec::new();
+        tree.pre_order(|&value| visited.push(value));
+        assert_eq!(visited, vec![4, 2, 1, 3, 6, 5, 7]);
+
+        visited.clear();
+        tree.post_order(|&value| visited.push(value));
+        assert_eq!(visited, vec![1, 3, 2, 5, 7, 6, 4]);
+
+        assert_eq!(tree.iter().rev().copied().collect::<Vec<_>>(), vec![7, 6, 5, 4, 3, 2, 1]);
+        assert_eq!(tree.into_iter().collect::<Vec<_>>(), vec![1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut tree = create_test_tree(&[5, 3, 8]);
+        tree.clear();
+
+        assert!(tree.is_empty());
+        assert_eq!(tree.height(), 0);
+        assert!(tree.insert(1));
+        assert_eq!(tree.size(), 1);
+    }
+}
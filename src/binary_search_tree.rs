@@ -1,82 +1,1944 @@
-struct Node<T: Ord> {
-    value: T,
-    left: Option<Box<Node<T>>>,
-    right: Option<Box<Node<T>>>,
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::iter::Peekable;
+use std::ops::{Bound, RangeBounds};
+
+use crate::compare::{Compare, Natural};
+
+// `size` counts the nodes in this subtree (itself included) and backs the order-statistic queries;
+// `meta` is per-node bookkeeping for the balanced variants built on the same node layout
+// (the AVL tree keeps each node's height there); the plain tree leaves it as ()
+pub(crate) struct Node<T, M = ()> {
+    pub(crate) value: T,
+    pub(crate) left: Option<Box<Node<T, M>>>,
+    pub(crate) right: Option<Box<Node<T, M>>>,
+    pub(crate) size: usize,
+    pub(crate) meta: M,
+}
+
+impl<T, M> Node<T, M> {
+    pub(crate) fn new(value: T, meta: M) -> Self {
+        Node {
+            value,
+            left: None,
+            right: None,
+            size: 1,
+            meta,
+        }
+    }
 }
 
-pub struct BinarySearchTree<T: Ord> {
+// every traversal below uses an explicit stack or queue instead of recursion,
+// so a degenerate tree (e.g. built from sorted input) can't overflow the call stack.
+// values are ordered by the comparator `C`, which defaults to the type's own Ord
+pub struct BinarySearchTree<T, C = Natural> {
     root: Option<Box<Node<T>>>,
     size: usize,
+    cmp: C,
 }
 
 impl<T> BinarySearchTree<T> {
     pub fn new() -> Self {
+        Self::with_comparator(Natural)
+    }
+}
 
+impl<T: Ord> BinarySearchTree<T> {
+    pub fn from_sorted_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+    {
+        Self::from_sorted_iter_by(iter, Natural)
+    }
+}
+
+impl<T, C> BinarySearchTree<T, C> {
+    pub fn with_comparator(cmp: C) -> Self {
+        BinarySearchTree {
+            root: None,
+            size: 0,
+            cmp,
+        }
     }
 
     pub fn is_empty(&self) -> bool {
-
+        self.root.is_none()
     }
 
     pub fn size(&self) -> usize {
-
+        self.size
     }
 
     pub fn clear(&mut self) {
-
+        tear_down(self.root.take());
+        self.size = 0;
     }
 
     pub fn height(&self) -> usize {
+        let mut height = 0;
+        let mut level = VecDeque::new();
+        if let Some(root) = self.root.as_deref() {
+            level.push_back(root);
+        }
+
+        while !level.is_empty() {
+            height += 1;
+            for _ in 0..level.len() {
+                let node = level.pop_front().unwrap();
+                if let Some(left) = node.left.as_deref() {
+                    level.push_back(left);
+                }
+                if let Some(right) = node.right.as_deref() {
+                    level.push_back(right);
+                }
+            }
+        }
+
+        height
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter::new(self.root.as_deref(), self.size)
+    }
+
+    pub fn pre_order_iter(&self) -> PreOrderIter<'_, T> {
+        PreOrderIter::new(self.root.as_deref())
+    }
 
+    pub fn post_order_iter(&self) -> PostOrderIter<'_, T> {
+        PostOrderIter::new(self.root.as_deref())
+    }
+
+    pub fn level_order_iter(&self) -> LevelOrderIter<'_, T> {
+        LevelOrderIter::new(self.root.as_deref())
     }
 }
 
-impl<T: Ord> BinarySearchTree<T> {
+impl<T, C: Compare<T>> BinarySearchTree<T, C> {
     pub fn insert(&mut self, value: T) -> bool {
+        // every node on the way down gains a descendant. a duplicate is only found at the end
+        // of the walk, and then a second walk takes those counts back
+        let mut current = &mut self.root;
+
+        while let Some(node) = current {
+            let ordering = self.cmp.compare(&value, &node.value);
+            if ordering == Ordering::Equal {
+                self.restore_sizes(&value, false);
+                return false;
+            }
 
+            node.size += 1;
+            current = if ordering == Ordering::Less { &mut node.left } else { &mut node.right };
+        }
+
+        *current = Some(Box::new(Node::new(value, ())));
+        self.size += 1;
+        true
     }
 
     pub fn contains(&self, value: &T) -> bool {
-
+        find(self.root.as_deref(), value, &self.cmp).is_some()
     }
 
     pub fn remove(&mut self, value: &T) -> Option<T> {
+        // every node on the way down loses a descendant; a miss walks down again to put the
+        // counts back
+        let mut current = &mut self.root;
+
+        loop {
+            let ordering = match current {
+                Some(node) => self.cmp.compare(value, &node.value),
+                None => {
+                    self.restore_sizes(value, true);
+                    return None;
+                }
+            };
+            if ordering == Ordering::Equal {
+                break;
+            }
 
+            let node = current.as_mut().unwrap();
+            node.size -= 1;
+            current = if ordering == Ordering::Less { &mut node.left } else { &mut node.right };
+        }
+
+        self.size -= 1;
+        Some(unlink(current))
     }
 
-    pub fn min(&self) -> Option<&T> {
+    // undoes the size updates of an insert or remove that turned out to be a no-op: every
+    // node above `value` (or above the empty link where it would go) gets one back or loses one
+    fn restore_sizes(&mut self, value: &T, increment: bool) {
+        let mut current = self.root.as_deref_mut();
+        while let Some(node) = current {
+            let ordering = self.cmp.compare(value, &node.value);
+            if ordering == Ordering::Equal {
+                break;
+            }
+
+            if increment {
+                node.size += 1;
+            } else {
+                node.size -= 1;
+            }
+            current = if ordering == Ordering::Less { node.left.as_deref_mut() } else { node.right.as_deref_mut() };
+        }
+    }
+
+    pub fn from_sorted_iter_by<I>(iter: I, cmp: C) -> Self
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let mut values: Vec<T> = iter.into_iter().collect();
+        // the O(n) build relies on the order, so fall back to sorting anything that isn't
+        if !values.is_sorted_by(|a, b| cmp.compare(a, b) != Ordering::Greater) {
+            values.sort_by(|a, b| cmp.compare(a, b));
+        }
+        values.dedup_by(|a, b| cmp.compare(a, b) == Ordering::Equal);
+
+        let mut tree = Self::with_comparator(cmp);
+        tree.rebuild(values);
+        tree
+    }
+
+    // splits off every value greater than or equal to `value`, returning them as a new tree;
+    // both halves are rebuilt perfectly balanced in O(n)
+    pub fn split_off(&mut self, value: &T) -> Self
+    where
+        C: Clone,
+    {
+        let mut lower: Vec<T> = self.drain().collect();
+        let split = lower.partition_point(|current| self.cmp.compare(current, value) == Ordering::Less);
+        let upper = lower.split_off(split);
+        self.rebuild(lower);
+
+        let mut tree = Self::with_comparator(self.cmp.clone());
+        tree.rebuild(upper);
+        tree
+    }
+
+    // moves every value out of `other` into this tree, leaving `other` empty; values present in
+    // both trees are kept once. The merged tree is rebuilt perfectly balanced in O(n + m)
+    pub fn append(&mut self, other: &mut Self) {
+        if other.is_empty() {
+            return;
+        }
+
+        let merged = merge_sorted(self.drain(), other.drain(), &self.cmp);
+        self.rebuild(merged);
+    }
+
+    // replaces the (empty) tree's contents with `values`, which must be strictly increasing
+    fn rebuild(&mut self, values: Vec<T>) {
+        self.size = values.len();
+        self.root = build_balanced(&mut values.into_iter(), self.size, &|_| ());
+    }
+
+    // empties the tree, handing back its values in order
+    fn drain(&mut self) -> IntoIter<T> {
+        IntoIter::new(self.root.take(), std::mem::take(&mut self.size))
+    }
+
+    // the set operations below are linear merges over the two in-order iterators
+
+    pub fn union<'a>(&'a self, other: &'a Self) -> Union<'a, T, C> {
+        Union::new(self.iter(), other.iter(), &self.cmp)
+    }
+
+    pub fn intersection<'a>(&'a self, other: &'a Self) -> Intersection<'a, T, C> {
+        Intersection::new(self.iter(), other.iter(), &self.cmp)
+    }
+
+    pub fn difference<'a>(&'a self, other: &'a Self) -> Difference<'a, T, C> {
+        Difference::new(self.iter(), other.iter(), &self.cmp)
+    }
+
+    pub fn symmetric_difference<'a>(&'a self, other: &'a Self) -> SymmetricDifference<'a, T, C> {
+        SymmetricDifference::new(self.iter(), other.iter(), &self.cmp)
+    }
+
+    pub fn is_subset(&self, other: &Self) -> bool {
+        if self.size > other.size {
+            return false;
+        }
 
+        is_subset(self.iter(), other.iter(), &self.cmp)
+    }
+
+    pub fn is_superset(&self, other: &Self) -> bool {
+        other.is_subset(self)
+    }
+
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+        self.intersection(other).next().is_none()
+    }
+
+    pub fn entry(&mut self, value: T) -> Entry<'_, T> {
+        // the path is kept as raw pointers so the entry can fix up the subtree sizes of every
+        // ancestor later on without walking down from the root a second time
+        let mut path = Vec::new();
+        let mut slot: *mut Option<Box<Node<T>>> = &mut self.root;
+
+        // SAFETY: `slot` always points at self.root or at a child link of a node reached from
+        // it, and nothing else touches the tree while the walk runs
+        unsafe {
+            while let Some(node) = (*slot).as_deref_mut() {
+                match self.cmp.compare(&value, &node.value) {
+                    Ordering::Less => {
+                        path.push(node as *mut Node<T>);
+                        slot = &mut node.left;
+                    }
+                    Ordering::Greater => {
+                        path.push(node as *mut Node<T>);
+                        slot = &mut node.right;
+                    }
+                    Ordering::Equal => {
+                        return Entry::Occupied(OccupiedEntry {
+                            value,
+                            slot,
+                            path,
+                            size: &mut self.size,
+                        });
+                    }
+                }
+            }
+        }
+
+        Entry::Vacant(VacantEntry {
+            value,
+            slot,
+            path,
+            size: &mut self.size,
+        })
+    }
+
+    pub fn min(&self) -> Option<&T> {
+        min(self.root.as_deref())
     }
 
     pub fn max(&self) -> Option<&T> {
+        max(self.root.as_deref())
+    }
+
+    pub fn floor(&self, value: &T) -> Option<&T> {
+        bound_below(self.root.as_deref(), value, true, &self.cmp)
+    }
+
+    pub fn ceiling(&self, value: &T) -> Option<&T> {
+        bound_above(self.root.as_deref(), value, true, &self.cmp)
+    }
 
+    pub fn predecessor(&self, value: &T) -> Option<&T> {
+        bound_below(self.root.as_deref(), value, false, &self.cmp)
     }
 
-    pub fn in_order<F>(&self, mut visit: F)
+    pub fn successor(&self, value: &T) -> Option<&T> {
+        bound_above(self.root.as_deref(), value, false, &self.cmp)
+    }
+
+    pub fn select(&self, k: usize) -> Option<&T> {
+        select(self.root.as_deref(), k)
+    }
+
+    pub fn rank(&self, value: &T) -> usize {
+        count_below(self.root.as_deref(), value, false, &self.cmp)
+    }
+
+    pub fn count_in_range<R>(&self, range: R) -> usize
     where
-        F: Fn(&T),
+        R: RangeBounds<T>,
     {
+        count_in_range(self.root.as_deref(), range, &self.cmp)
+    }
 
+    pub fn range<R>(&self, range: R) -> Range<'_, T>
+    where
+        R: RangeBounds<T>,
+    {
+        Range::new(self.root.as_deref(), range, &self.cmp)
     }
 
-    pub fn pre_order<F>(&self, mut visit: F)
+    pub fn in_order<F>(&self, visit: F)
     where
-        F: Fn(&T),
+        F: FnMut(&T),
     {
+        self.iter().for_each(visit);
+    }
 
+    pub fn pre_order<F>(&self, visit: F)
+    where
+        F: FnMut(&T),
+    {
+        self.pre_order_iter().for_each(visit);
     }
 
-    pub fn post_order<F>(&self, mut visit: F)
+    pub fn post_order<F>(&self, visit: F)
     where
-        F: Fn(&T),
+        F: FnMut(&T),
     {
+        self.post_order_iter().for_each(visit);
+    }
+
+    pub fn level_order<F>(&self, visit: F)
+    where
+        F: FnMut(&T),
+    {
+        self.level_order_iter().for_each(visit);
+    }
+}
+
+impl<T, C: Default> Default for BinarySearchTree<T, C> {
+    fn default() -> Self {
+        Self::with_comparator(C::default())
+    }
+}
+
+impl<T, C> Drop for BinarySearchTree<T, C> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+// the lookups below only read the tree's shape, so the balanced variants share them
+
+pub(crate) fn find<'a, T, M>(
+    mut current: Option<&'a Node<T, M>>,
+    value: &T,
+    cmp: &impl Compare<T>,
+) -> Option<&'a Node<T, M>> {
+    while let Some(node) = current {
+        match cmp.compare(value, &node.value) {
+            Ordering::Less => current = node.left.as_deref(),
+            Ordering::Greater => current = node.right.as_deref(),
+            Ordering::Equal => return Some(node),
+        }
+    }
+
+    None
+}
+
+pub(crate) fn min<T, M>(root: Option<&Node<T, M>>) -> Option<&T> {
+    let mut current = root?;
+    while let Some(left) = current.left.as_deref() {
+        current = left;
+    }
+    Some(&current.value)
+}
+
+pub(crate) fn max<T, M>(root: Option<&Node<T, M>>) -> Option<&T> {
+    let mut current = root?;
+    while let Some(right) = current.right.as_deref() {
+        current = right;
+    }
+    Some(&current.value)
+}
+
+pub(crate) fn bound_below<'a, T, M>(
+    mut current: Option<&'a Node<T, M>>,
+    value: &T,
+    inclusive: bool,
+    cmp: &impl Compare<T>,
+) -> Option<&'a T> {
+    let mut best = None;
+
+    while let Some(node) = current {
+        match cmp.compare(&node.value, value) {
+            Ordering::Equal if inclusive => return Some(&node.value),
+            Ordering::Less => {
+                best = Some(&node.value);
+                current = node.right.as_deref();
+            }
+            _ => current = node.left.as_deref(),
+        }
+    }
+
+    best
+}
+
+pub(crate) fn bound_above<'a, T, M>(
+    mut current: Option<&'a Node<T, M>>,
+    value: &T,
+    inclusive: bool,
+    cmp: &impl Compare<T>,
+) -> Option<&'a T> {
+    let mut best = None;
+
+    while let Some(node) = current {
+        match cmp.compare(&node.value, value) {
+            Ordering::Equal if inclusive => return Some(&node.value),
+            Ordering::Greater => {
+                best = Some(&node.value);
+                current = node.left.as_deref();
+            }
+            _ => current = node.right.as_deref(),
+        }
+    }
+
+    best
+}
 
+// builds a perfectly balanced tree out of the next `count` values, which arrive in order; the
+// left subtree is built first so the values are consumed in-order, and recursion depth is log2(n)
+// `meta` gives each node's bookkeeping from the size of the subtree it roots
+pub(crate) fn build_balanced<T, M>(
+    values: &mut impl Iterator<Item = T>,
+    count: usize,
+    meta: &impl Fn(usize) -> M,
+) -> Option<Box<Node<T, M>>> {
+    if count == 0 {
+        return None;
     }
 
-    pub fn level_order<F>(&self, mut visit: F)
+    let left_count = count / 2;
+    let left = build_balanced(values, left_count, meta);
+    let mut node = Box::new(Node::new(values.next().unwrap(), meta(count)));
+    node.left = left;
+    node.right = build_balanced(values, count - left_count - 1, meta);
+    node.size = count;
+    Some(node)
+}
+
+// merges two strictly increasing sequences into one; a value present in both is kept once,
+// taking the copy from `ours`
+pub(crate) fn merge_sorted<T>(
+    ours: impl ExactSizeIterator<Item = T>,
+    theirs: impl ExactSizeIterator<Item = T>,
+    cmp: &impl Compare<T>,
+) -> Vec<T> {
+    let mut merged = Vec::with_capacity(ours.len() + theirs.len());
+    let mut ours = ours.peekable();
+    let mut theirs = theirs.peekable();
+
+    loop {
+        let ordering = match (ours.peek(), theirs.peek()) {
+            (Some(a), Some(b)) => cmp.compare(a, b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => break,
+        };
+
+        match ordering {
+            Ordering::Less => merged.extend(ours.next()),
+            Ordering::Greater => merged.extend(theirs.next()),
+            Ordering::Equal => {
+                merged.extend(ours.next());
+                theirs.next();
+            }
+        }
+    }
+    merged
+}
+
+pub(crate) fn is_subset<'a, T: 'a, M: 'a>(
+    ours: Iter<'a, T, M>,
+    theirs: Iter<'a, T, M>,
+    cmp: &impl Compare<T>,
+) -> bool {
+    let mut theirs = theirs.peekable();
+    for value in ours {
+        // skip everything in `theirs` that sorts before the value we're looking for
+        while theirs.next_if(|candidate| cmp.compare(candidate, value) == Ordering::Less).is_some() {}
+        if theirs.next_if(|candidate| cmp.compare(candidate, value) == Ordering::Equal).is_none() {
+            return false;
+        }
+    }
+    true
+}
+
+pub(crate) fn subtree_size<T, M>(link: &Option<Box<Node<T, M>>>) -> usize {
+    link.as_ref().map_or(0, |node| node.size)
+}
+
+// the k-th smallest value (counting from 0), found by steering with the left subtree sizes
+pub(crate) fn select<T, M>(mut current: Option<&Node<T, M>>, mut k: usize) -> Option<&T> {
+    while let Some(node) = current {
+        let left_size = subtree_size(&node.left);
+        match k.cmp(&left_size) {
+            Ordering::Less => current = node.left.as_deref(),
+            Ordering::Equal => return Some(&node.value),
+            Ordering::Greater => {
+                k -= left_size + 1;
+                current = node.right.as_deref();
+            }
+        }
+    }
+
+    None
+}
+
+// how many values are below `value` (or at most `value`, when inclusive)
+pub(crate) fn count_below<T, M>(
+    mut current: Option<&Node<T, M>>,
+    value: &T,
+    inclusive: bool,
+    cmp: &impl Compare<T>,
+) -> usize {
+    let mut count = 0;
+
+    while let Some(node) = current {
+        let below = match cmp.compare(&node.value, value) {
+            Ordering::Less => true,
+            Ordering::Equal => inclusive,
+            Ordering::Greater => false,
+        };
+        if below {
+            count += subtree_size(&node.left) + 1;
+            current = node.right.as_deref();
+        } else {
+            current = node.left.as_deref();
+        }
+    }
+
+    count
+}
+
+pub(crate) fn count_in_range<T, M, R>(root: Option<&Node<T, M>>, range: R, cmp: &impl Compare<T>) -> usize
+where
+    R: RangeBounds<T>,
+{
+    let before_start = match range.start_bound() {
+        Bound::Included(start) => count_below(root, start, false, cmp),
+        Bound::Excluded(start) => count_below(root, start, true, cmp),
+        Bound::Unbounded => 0,
+    };
+    let through_end = match range.end_bound() {
+        Bound::Included(end) => count_below(root, end, true, cmp),
+        Bound::Excluded(end) => count_below(root, end, false, cmp),
+        Bound::Unbounded => root.map_or(0, |node| node.size),
+    };
+
+    through_end.saturating_sub(before_start)
+}
+
+pub(crate) fn tear_down<T, M>(root: Option<Box<Node<T, M>>>) {
+    // dropping the root directly would recurse once per level, so take the tree apart by hand
+    let mut stack = Vec::new();
+    stack.extend(root);
+
+    while let Some(mut node) = stack.pop() {
+        stack.extend(node.left.take());
+        stack.extend(node.right.take());
+    }
+}
+
+// detaches the node in `slot` and splices its children back in; the sizes of the slot's
+// ancestors must already account for the removal
+fn unlink<T>(slot: &mut Option<Box<Node<T>>>) -> T {
+    let mut node = slot.take().unwrap();
+    *slot = match (node.left.take(), node.right.take()) {
+        (None, None) => None,
+        (Some(left), None) => Some(left),
+        (None, Some(right)) => Some(right),
+        (Some(left), Some(right)) => {
+            // two children: the in-order successor (smallest value on the right) takes this node's place
+            let mut right = Some(right);
+            let mut successor = take_min(&mut right);
+            successor.left = Some(left);
+            successor.right = right;
+            successor.size = node.size - 1;
+            Some(successor)
+        }
+    };
+    node.value
+}
+
+fn take_min<T>(slot: &mut Option<Box<Node<T>>>) -> Box<Node<T>> {
+    let mut current = slot;
+    while current.as_ref().unwrap().left.is_some() {
+        let node = current.as_mut().unwrap();
+        node.size -= 1;
+        current = &mut node.left;
+    }
+
+    let mut min = current.take().unwrap();
+    *current = min.right.take();
+    min
+}
+
+// the value is its own key, so unlike TreeMap's entry there is no or_insert_with or
+// and_modify: the value is already built by the time there's an entry, and changing a stored
+// value in place could move it out of order. OccupiedEntry::replace is the safe way to update
+// one, swapping in the entry's value, which compares equal and so keeps the same place
+pub enum Entry<'a, T> {
+    Occupied(OccupiedEntry<'a, T>),
+    Vacant(VacantEntry<'a, T>),
+}
+
+// both entry kinds point into the tree they were created from: `slot` is the link that holds
+// (or would hold) the value and `path` lists its ancestors, and the borrow of the tree's size
+// keeps the tree itself mutably borrowed for as long as the entry lives, so no node on the
+// path can be moved or freed and the raw pointers stay valid
+pub struct OccupiedEntry<'a, T> {
+    value: T, // the value the entry was looked up with
+    slot: *mut Option<Box<Node<T>>>,
+    path: Vec<*mut Node<T>>,
+    size: &'a mut usize,
+}
+
+pub struct VacantEntry<'a, T> {
+    value: T,
+    slot: *mut Option<Box<Node<T>>>,
+    path: Vec<*mut Node<T>>,
+    size: &'a mut usize,
+}
+
+impl<'a, T> Entry<'a, T> {
+    pub fn get(&self) -> &T {
+        match self {
+            Entry::Occupied(entry) => entry.get(),
+            Entry::Vacant(entry) => entry.get(),
+        }
+    }
+
+    pub fn or_insert(self) -> &'a T {
+        match self {
+            Entry::Occupied(entry) => entry.into_ref(),
+            Entry::Vacant(entry) => entry.insert(),
+        }
+    }
+}
+
+impl<'a, T> OccupiedEntry<'a, T> {
+    pub fn get(&self) -> &T {
+        // SAFETY: the slot is occupied and the tree stays borrowed through `size` (see above)
+        unsafe { &(*self.slot).as_ref().unwrap().value }
+    }
+
+    pub fn into_ref(self) -> &'a T {
+        // SAFETY: as in get; the tree borrow lasts for 'a, so the reference can too
+        unsafe { &(*self.slot).as_ref().unwrap().value }
+    }
+
+    // stores the entry's value in place of the equal one already in the tree, and returns
+    // the old one
+    pub fn replace(self) -> T {
+        // SAFETY: as in get
+        let node = unsafe { (*self.slot).as_mut().unwrap() };
+        std::mem::replace(&mut node.value, self.value)
+    }
+
+    pub fn remove_entry(self) -> T {
+        // SAFETY: the tree stays borrowed through `size` (see above), so the path and the slot
+        // still point at live nodes, and unlinking the slot's node touches nothing on the path
+        unsafe {
+            for &ancestor in &self.path {
+                (*ancestor).size -= 1;
+            }
+            *self.size -= 1;
+            unlink(&mut *self.slot)
+        }
+    }
+}
+
+impl<'a, T> VacantEntry<'a, T> {
+    pub fn get(&self) -> &T {
+        &self.value
+    }
+
+    pub fn into_value(self) -> T {
+        self.value
+    }
+
+    pub fn insert(self) -> &'a T {
+        // SAFETY: the tree stays borrowed through `size` (see above), so the path still points
+        // at live nodes and the slot is the empty link the value belongs in
+        unsafe {
+            for &ancestor in &self.path {
+                (*ancestor).size += 1;
+            }
+            *self.size += 1;
+            *self.slot = Some(Box::new(Node::new(self.value, ())));
+            &(*self.slot).as_ref().unwrap().value
+        }
+    }
+}
+
+// in-order iterator; the two stacks hold the unvisited left and right spines,
+// and `remaining` stops the two ends from crossing
+pub struct Iter<'a, T, M = ()> {
+    front: Vec<&'a Node<T, M>>,
+    back: Vec<&'a Node<T, M>>,
+    remaining: usize,
+}
+
+impl<'a, T, M> Iter<'a, T, M> {
+    pub(crate) fn new(root: Option<&'a Node<T, M>>, size: usize) -> Self {
+        let mut iter = Iter {
+            front: Vec::new(),
+            back: Vec::new(),
+            remaining: size,
+        };
+        iter.push_left_spine(root);
+        iter.push_right_spine(root);
+        iter
+    }
+
+    fn push_left_spine(&mut self, mut current: Option<&'a Node<T, M>>) {
+        while let Some(node) = current {
+            self.front.push(node);
+            current = node.left.as_deref();
+        }
+    }
+
+    fn push_right_spine(&mut self, mut current: Option<&'a Node<T, M>>) {
+        while let Some(node) = current {
+            self.back.push(node);
+            current = node.right.as_deref();
+        }
+    }
+}
+
+impl<'a, T, M> Iterator for Iter<'a, T, M> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let node = self.front.pop()?;
+        self.push_left_spine(node.right.as_deref());
+        self.remaining -= 1;
+        Some(&node.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T, M> DoubleEndedIterator for Iter<'_, T, M> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let node = self.back.pop()?;
+        self.push_right_spine(node.left.as_deref());
+        self.remaining -= 1;
+        Some(&node.value)
+    }
+}
+
+impl<T, M> ExactSizeIterator for Iter<'_, T, M> {}
+
+pub struct IntoIter<T, M = ()> {
+    stack: Vec<Box<Node<T, M>>>,
+    remaining: usize,
+}
+
+impl<T, M> IntoIter<T, M> {
+    pub(crate) fn new(root: Option<Box<Node<T, M>>>, size: usize) -> Self {
+        let mut iter = IntoIter {
+            stack: Vec::new(),
+            remaining: size,
+        };
+        iter.push_left_spine(root);
+        iter
+    }
+
+    fn push_left_spine(&mut self, mut current: Option<Box<Node<T, M>>>) {
+        while let Some(mut node) = current {
+            current = node.left.take();
+            self.stack.push(node);
+        }
+    }
+}
+
+impl<T, M> Iterator for IntoIter<T, M> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut node = self.stack.pop()?;
+        self.push_left_spine(node.right.take());
+        self.remaining -= 1;
+        Some(node.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T, M> ExactSizeIterator for IntoIter<T, M> {}
+
+impl<T, M> Drop for IntoIter<T, M> {
+    fn drop(&mut self) {
+        // the stacked nodes still own whole right subtrees, so drain them one node at a time
+        for _ in &mut *self {}
+    }
+}
+
+pub struct Range<'a, T, M = ()> {
+    front: Vec<&'a Node<T, M>>,
+    back: Vec<&'a Node<T, M>>,
+}
+
+impl<'a, T, M> Range<'a, T, M> {
+    pub(crate) fn new<R>(root: Option<&'a Node<T, M>>, range: R, cmp: &impl Compare<T>) -> Self
     where
-        F: Fn(&T),
+        R: RangeBounds<T>,
     {
+        // front holds the path to the smallest value above the start bound, back the path
+        // to the largest value below the end bound; both are built in a single descent each
+        let mut front = Vec::new();
+        let mut current = root;
+        while let Some(node) = current {
+            let above_start = match range.start_bound() {
+                Bound::Included(start) => cmp.compare(&node.value, start) != Ordering::Less,
+                Bound::Excluded(start) => cmp.compare(&node.value, start) == Ordering::Greater,
+                Bound::Unbounded => true,
+            };
+            if above_start {
+                front.push(node);
+                current = node.left.as_deref();
+            } else {
+                current = node.right.as_deref();
+            }
+        }
+
+        let mut back = Vec::new();
+        let mut current = root;
+        while let Some(node) = current {
+            let below_end = match range.end_bound() {
+                Bound::Included(end) => cmp.compare(&node.value, end) != Ordering::Greater,
+                Bound::Excluded(end) => cmp.compare(&node.value, end) == Ordering::Less,
+                Bound::Unbounded => true,
+            };
+            if below_end {
+                back.push(node);
+                current = node.right.as_deref();
+            } else {
+                current = node.left.as_deref();
+            }
+        }
+
+        // the ends start out crossed when nothing lies between the bounds
+        let is_empty = match (front.last(), back.last()) {
+            (Some(first), Some(last)) => cmp.compare(&first.value, &last.value) == Ordering::Greater,
+            _ => true,
+        };
+        if is_empty {
+            front.clear();
+            back.clear();
+        }
+
+        Range { front, back }
+    }
+}
+
+impl<'a, T, M> Iterator for Range<'a, T, M> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.front.pop()?;
+        let mut current = node.right.as_deref();
+        while let Some(child) = current {
+            self.front.push(child);
+            current = child.left.as_deref();
+        }
+
+        // both stacks walk the same in-order sequence, so the ends have met once
+        // one side hands out the node that is waiting on top of the other side
+        if self.back.last().is_some_and(|&top| std::ptr::eq(top, node)) {
+            self.front.clear();
+            self.back.clear();
+        }
+        Some(&node.value)
+    }
+}
+
+impl<T, M> DoubleEndedIterator for Range<'_, T, M> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let node = self.back.pop()?;
+        let mut current = node.left.as_deref();
+        while let Some(child) = current {
+            self.back.push(child);
+            current = child.right.as_deref();
+        }
+
+        if self.front.last().is_some_and(|&top| std::ptr::eq(top, node)) {
+            self.front.clear();
+            self.back.clear();
+        }
+        Some(&node.value)
+    }
+}
+
+pub struct Union<'a, T, C = Natural, M = ()> {
+    a: Peekable<Iter<'a, T, M>>,
+    b: Peekable<Iter<'a, T, M>>,
+    cmp: &'a C,
+}
+
+impl<'a, T, C, M> Union<'a, T, C, M> {
+    pub(crate) fn new(a: Iter<'a, T, M>, b: Iter<'a, T, M>, cmp: &'a C) -> Self {
+        Union {
+            a: a.peekable(),
+            b: b.peekable(),
+            cmp,
+        }
+    }
+}
+
+impl<'a, T, C: Compare<T>, M> Iterator for Union<'a, T, C, M> {
+    type Item = &'a T;
 
+    fn next(&mut self) -> Option<Self::Item> {
+        let ordering = match (self.a.peek(), self.b.peek()) {
+            (Some(a), Some(b)) => self.cmp.compare(a, b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => return None,
+        };
+
+        match ordering {
+            Ordering::Less => self.a.next(),
+            Ordering::Greater => self.b.next(),
+            Ordering::Equal => {
+                self.b.next();
+                self.a.next()
+            }
+        }
+    }
+}
+
+pub struct Intersection<'a, T, C = Natural, M = ()> {
+    a: Peekable<Iter<'a, T, M>>,
+    b: Peekable<Iter<'a, T, M>>,
+    cmp: &'a C,
+}
+
+impl<'a, T, C, M> Intersection<'a, T, C, M> {
+    pub(crate) fn new(a: Iter<'a, T, M>, b: Iter<'a, T, M>, cmp: &'a C) -> Self {
+        Intersection {
+            a: a.peekable(),
+            b: b.peekable(),
+            cmp,
+        }
+    }
+}
+
+impl<'a, T, C: Compare<T>, M> Iterator for Intersection<'a, T, C, M> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.cmp.compare(self.a.peek()?, self.b.peek()?) {
+                Ordering::Less => {
+                    self.a.next();
+                }
+                Ordering::Greater => {
+                    self.b.next();
+                }
+                Ordering::Equal => {
+                    self.b.next();
+                    return self.a.next();
+                }
+            }
+        }
+    }
+}
+
+pub struct Difference<'a, T, C = Natural, M = ()> {
+    a: Peekable<Iter<'a, T, M>>,
+    b: Peekable<Iter<'a, T, M>>,
+    cmp: &'a C,
+}
+
+impl<'a, T, C, M> Difference<'a, T, C, M> {
+    pub(crate) fn new(a: Iter<'a, T, M>, b: Iter<'a, T, M>, cmp: &'a C) -> Self {
+        Difference {
+            a: a.peekable(),
+            b: b.peekable(),
+            cmp,
+        }
+    }
+}
+
+impl<'a, T, C: Compare<T>, M> Iterator for Difference<'a, T, C, M> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let a = self.a.peek()?;
+            let Some(b) = self.b.peek() else {
+                return self.a.next();
+            };
+
+            match self.cmp.compare(a, b) {
+                Ordering::Less => return self.a.next(),
+                Ordering::Greater => {
+                    self.b.next();
+                }
+                Ordering::Equal => {
+                    self.a.next();
+                    self.b.next();
+                }
+            }
+        }
+    }
+}
+
+pub struct SymmetricDifference<'a, T, C = Natural, M = ()> {
+    a: Peekable<Iter<'a, T, M>>,
+    b: Peekable<Iter<'a, T, M>>,
+    cmp: &'a C,
+}
+
+impl<'a, T, C, M> SymmetricDifference<'a, T, C, M> {
+    pub(crate) fn new(a: Iter<'a, T, M>, b: Iter<'a, T, M>, cmp: &'a C) -> Self {
+        SymmetricDifference {
+            a: a.peekable(),
+            b: b.peekable(),
+            cmp,
+        }
+    }
+}
+
+impl<'a, T, C: Compare<T>, M> Iterator for SymmetricDifference<'a, T, C, M> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let ordering = match (self.a.peek(), self.b.peek()) {
+                (Some(a), Some(b)) => self.cmp.compare(a, b),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => return None,
+            };
+
+            match ordering {
+                Ordering::Less => return self.a.next(),
+                Ordering::Greater => return self.b.next(),
+                Ordering::Equal => {
+                    self.a.next();
+                    self.b.next();
+                }
+            }
+        }
+    }
+}
+
+pub struct PreOrderIter<'a, T, M = ()> {
+    stack: Vec<&'a Node<T, M>>,
+}
+
+impl<'a, T, M> PreOrderIter<'a, T, M> {
+    pub(crate) fn new(root: Option<&'a Node<T, M>>) -> Self {
+        PreOrderIter {
+            stack: root.into_iter().collect(),
+        }
+    }
+}
+
+impl<'a, T, M> Iterator for PreOrderIter<'a, T, M> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        // right goes on first so the left subtree is visited first
+        if let Some(right) = node.right.as_deref() {
+            self.stack.push(right);
+        }
+        if let Some(left) = node.left.as_deref() {
+            self.stack.push(left);
+        }
+        Some(&node.value)
     }
-}
\ No newline at end of file
+}
+
+pub struct PostOrderIter<'a, T, M = ()> {
+    // the flag marks nodes whose children have already been scheduled
+    stack: Vec<(&'a Node<T, M>, bool)>,
+}
+
+impl<'a, T, M> PostOrderIter<'a, T, M> {
+    pub(crate) fn new(root: Option<&'a Node<T, M>>) -> Self {
+        PostOrderIter {
+            stack: root.map(|root| (root, false)).into_iter().collect(),
+        }
+    }
+}
+
+impl<'a, T, M> Iterator for PostOrderIter<'a, T, M> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((node, expanded)) = self.stack.pop() {
+            if expanded {
+                return Some(&node.value);
+            }
+
+            self.stack.push((node, true));
+            if let Some(right) = node.right.as_deref() {
+                self.stack.push((right, false));
+            }
+            if let Some(left) = node.left.as_deref() {
+                self.stack.push((left, false));
+            }
+        }
+
+        None
+    }
+}
+
+pub struct LevelOrderIter<'a, T, M = ()> {
+    queue: VecDeque<&'a Node<T, M>>,
+}
+
+impl<'a, T, M> LevelOrderIter<'a, T, M> {
+    pub(crate) fn new(root: Option<&'a Node<T, M>>) -> Self {
+        LevelOrderIter {
+            queue: root.into_iter().collect(),
+        }
+    }
+}
+
+impl<'a, T, M> Iterator for LevelOrderIter<'a, T, M> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.queue.pop_front()?;
+        if let Some(left) = node.left.as_deref() {
+            self.queue.push_back(left);
+        }
+        if let Some(right) = node.right.as_deref() {
+            self.queue.push_back(right);
+        }
+        Some(&node.value)
+    }
+}
+
+impl<'a, T, C> IntoIterator for &'a BinarySearchTree<T, C> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T, C> IntoIterator for BinarySearchTree<T, C> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(mut self) -> Self::IntoIter {
+        let size = std::mem::take(&mut self.size);
+        IntoIter::new(self.root.take(), size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compare::Reversed;
+
+    fn create_test_tree(values: &[i32]) -> BinarySearchTree<i32> {
+        let mut tree = BinarySearchTree::new();
+        for &value in values {
+            tree.insert(value);
+        }
+        tree
+    }
+
+    fn collect_in_order(tree: &BinarySearchTree<i32>) -> Vec<i32> {
+        tree.iter().copied().collect()
+    }
+
+    #[test]
+    fn test_new_is_empty() {
+        let tree: BinarySearchTree<i32> = BinarySearchTree::new();
+        assert!(tree.is_empty());
+        assert_eq!(tree.size(), 0);
+        assert_eq!(tree.height(), 0);
+        assert_eq!(tree.min(), None);
+        assert_eq!(tree.max(), None);
+    }
+
+    #[test]
+    fn test_insert_increases_size() {
+        let mut tree = BinarySearchTree::new();
+
+        assert!(tree.insert(10));
+        assert!(!tree.is_empty());
+        assert_eq!(tree.size(), 1);
+
+        assert!(tree.insert(5));
+        assert!(tree.insert(15));
+        assert_eq!(tree.size(), 3);
+    }
+
+    #[test]
+    fn test_insert_duplicate_is_rejected() {
+        let mut tree = create_test_tree(&[10, 5, 15]);
+
+        assert!(!tree.insert(10));
+        assert!(!tree.insert(5));
+        assert_eq!(tree.size(), 3);
+        assert_eq!(collect_in_order(&tree), vec![5, 10, 15]);
+    }
+
+    #[test]
+    fn test_contains() {
+        let tree = create_test_tree(&[50, 30, 70, 20, 40, 60, 80]);
+
+        for value in [20, 30, 40, 50, 60, 70, 80] {
+            assert!(tree.contains(&value));
+        }
+        for value in [0, 25, 55, 90] {
+            assert!(!tree.contains(&value));
+        }
+    }
+
+    #[test]
+    fn test_min_max() {
+        let mut tree = create_test_tree(&[50, 30, 70, 20, 40, 60, 80]);
+        assert_eq!(tree.min(), Some(&20));
+        assert_eq!(tree.max(), Some(&80));
+
+        tree.remove(&20);
+        tree.remove(&80);
+        assert_eq!(tree.min(), Some(&30));
+        assert_eq!(tree.max(), Some(&70));
+    }
+
+    #[test]
+    fn test_remove_leaf() {
+        let mut tree = create_test_tree(&[50, 30, 70]);
+
+        assert_eq!(tree.remove(&30), Some(30));
+        assert_eq!(tree.size(), 2);
+        assert!(!tree.contains(&30));
+        assert_eq!(collect_in_order(&tree), vec![50, 70]);
+    }
+
+    #[test]
+    fn test_remove_node_with_one_child() {
+        let mut tree = create_test_tree(&[50, 30, 20, 70, 80]);
+
+        assert_eq!(tree.remove(&30), Some(30));
+        assert_eq!(collect_in_order(&tree), vec![20, 50, 70, 80]);
+
+        assert_eq!(tree.remove(&70), Some(70));
+        assert_eq!(collect_in_order(&tree), vec![20, 50, 80]);
+        assert_eq!(tree.size(), 3);
+    }
+
+    #[test]
+    fn test_remove_node_with_two_children() {
+        let mut tree = create_test_tree(&[50, 30, 70, 20, 40, 60, 80, 65]);
+
+        assert_eq!(tree.remove(&70), Some(70));
+        assert_eq!(collect_in_order(&tree), vec![20, 30, 40, 50, 60, 65, 80]);
+
+        assert_eq!(tree.remove(&50), Some(50));
+        assert_eq!(collect_in_order(&tree), vec![20, 30, 40, 60, 65, 80]);
+        assert_eq!(tree.size(), 6);
+        assert!(tree.contains(&65));
+    }
+
+    #[test]
+    fn test_remove_missing_and_until_empty() {
+        let mut tree = create_test_tree(&[2, 1, 3]);
+        assert_eq!(tree.remove(&4), None);
+        assert_eq!(tree.size(), 3);
+
+        assert_eq!(tree.remove(&2), Some(2));
+        assert_eq!(tree.remove(&1), Some(1));
+        assert_eq!(tree.remove(&3), Some(3));
+        assert!(tree.is_empty());
+        assert_eq!(tree.remove(&3), None);
+    }
+
+    #[test]
+    fn test_height() {
+        let mut tree = BinarySearchTree::new();
+        tree.insert(50);
+        assert_eq!(tree.height(), 1);
+
+        for value in [30, 70, 20, 40, 60, 80] {
+            tree.insert(value);
+        }
+        assert_eq!(tree.height(), 3);
+
+        tree.insert(10);
+        assert_eq!(tree.height(), 4);
+    }
+
+    #[test]
+    fn test_traversals() {
+        let tree = create_test_tree(&[50, 30, 70, 20, 40, 60, 80]);
+
+        let mut visited = Vec::new();
+        tree.in_order(|&value| visited.push(value));
+        assert_eq!(visited, vec![20, 30, 40, 50, 60, 70, 80]);
+
+        visited.clear();
+        tree.pre_order(|&value| visited.push(value));
+        assert_eq!(visited, vec![50, 30, 20, 40, 70, 60, 80]);
+
+        visited.clear();
+        tree.post_order(|&value| visited.push(value));
+        assert_eq!(visited, vec![20, 40, 30, 60, 80, 70, 50]);
+
+        visited.clear();
+        tree.level_order(|&value| visited.push(value));
+        assert_eq!(visited, vec![50, 30, 70, 20, 40, 60, 80]);
+    }
+
+    #[test]
+    fn test_traversal_iterators() {
+        let tree = create_test_tree(&[50, 30, 70, 20, 40, 60, 80]);
+
+        assert_eq!(tree.pre_order_iter().copied().collect::<Vec<_>>(), vec![50, 30, 20, 40, 70, 60, 80]);
+        assert_eq!(tree.post_order_iter().copied().collect::<Vec<_>>(), vec![20, 40, 30, 60, 80, 70, 50]);
+        assert_eq!(tree.level_order_iter().copied().collect::<Vec<_>>(), vec![50, 30, 70, 20, 40, 60, 80]);
+
+        let empty: BinarySearchTree<i32> = BinarySearchTree::new();
+        assert_eq!(empty.iter().next(), None);
+        assert_eq!(empty.pre_order_iter().next(), None);
+        assert_eq!(empty.post_order_iter().next(), None);
+        assert_eq!(empty.level_order_iter().next(), None);
+    }
+
+    #[test]
+    fn test_iter_is_double_ended() {
+        let tree = create_test_tree(&[50, 30, 70, 20, 40, 60, 80]);
+
+        assert_eq!(tree.iter().rev().copied().collect::<Vec<_>>(), vec![80, 70, 60, 50, 40, 30, 20]);
+
+        let mut iter = tree.iter();
+        assert_eq!(iter.len(), 7);
+        assert_eq!(iter.next(), Some(&20));
+        assert_eq!(iter.next_back(), Some(&80));
+        assert_eq!(iter.next_back(), Some(&70));
+        assert_eq!(iter.next(), Some(&30));
+        assert_eq!(iter.len(), 3);
+
+        let middle: Vec<_> = iter.copied().collect();
+        assert_eq!(middle, vec![40, 50, 60]);
+    }
+
+    #[test]
+    fn test_iterator_early_exit_and_adapters() {
+        let tree = create_test_tree(&[50, 30, 70, 20, 40, 60, 80]);
+
+        assert_eq!(tree.iter().find(|&&value| value > 45), Some(&50));
+        assert_eq!(tree.iter().filter(|&&value| value % 20 == 0).count(), 4);
+        assert_eq!(tree.level_order_iter().take(3).copied().collect::<Vec<_>>(), vec![50, 30, 70]);
+
+        let mut sum = 0;
+        for value in &tree {
+            sum += value;
+        }
+        assert_eq!(sum, 350);
+    }
+
+    #[test]
+    fn test_into_iter() {
+        let tree = create_test_tree(&[50, 30, 70, 20, 40, 60, 80]);
+        let mut iter = tree.into_iter();
+        assert_eq!(iter.len(), 7);
+        assert_eq!(iter.next(), Some(20));
+        assert_eq!(iter.collect::<Vec<_>>(), vec![30, 40, 50, 60, 70, 80]);
+
+        let mut tree = BinarySearchTree::new();
+        tree.insert(String::from("b"));
+        tree.insert(String::from("a"));
+        tree.insert(String::from("c"));
+        let mut iter = tree.into_iter();
+        assert_eq!(iter.next(), Some(String::from("a")));
+        drop(iter);
+    }
+
+    #[test]
+    fn test_floor_and_ceiling() {
+        let tree = create_test_tree(&[50, 30, 70, 20, 40, 60, 80]);
+
+        assert_eq!(tree.floor(&45), Some(&40));
+        assert_eq!(tree.floor(&50), Some(&50));
+        assert_eq!(tree.floor(&100), Some(&80));
+        assert_eq!(tree.floor(&10), None);
+
+        assert_eq!(tree.ceiling(&45), Some(&50));
+        assert_eq!(tree.ceiling(&60), Some(&60));
+        assert_eq!(tree.ceiling(&10), Some(&20));
+        assert_eq!(tree.ceiling(&100), None);
+    }
+
+    #[test]
+    fn test_predecessor_and_successor() {
+        let tree = create_test_tree(&[50, 30, 70, 20, 40, 60, 80]);
+
+        assert_eq!(tree.predecessor(&50), Some(&40));
+        assert_eq!(tree.predecessor(&55), Some(&50));
+        assert_eq!(tree.predecessor(&20), None);
+
+        assert_eq!(tree.successor(&50), Some(&60));
+        assert_eq!(tree.successor(&40), Some(&50));
+        assert_eq!(tree.successor(&45), Some(&50));
+        assert_eq!(tree.successor(&80), None);
+
+        let empty: BinarySearchTree<i32> = BinarySearchTree::new();
+        assert_eq!(empty.floor(&1), None);
+        assert_eq!(empty.successor(&1), None);
+    }
+
+    #[test]
+    fn test_range_bounds() {
+        let tree = create_test_tree(&[50, 30, 70, 20, 40, 60, 80]);
+        let collect = |range: Range<'_, i32>| range.copied().collect::<Vec<_>>();
+
+        assert_eq!(collect(tree.range(30..70)), vec![30, 40, 50, 60]);
+        assert_eq!(collect(tree.range(30..=70)), vec![30, 40, 50, 60, 70]);
+        assert_eq!(collect(tree.range(35..65)), vec![40, 50, 60]);
+        assert_eq!(collect(tree.range(..40)), vec![20, 30]);
+        assert_eq!(collect(tree.range(75..)), vec![80]);
+        assert_eq!(collect(tree.range(..)), vec![20, 30, 40, 50, 60, 70, 80]);
+        assert_eq!(
+            collect(tree.range((Bound::Excluded(30), Bound::Excluded(60)))),
+            vec![40, 50]
+        );
+    }
+
+    #[test]
+    fn test_range_empty() {
+        let tree = create_test_tree(&[50, 30, 70, 20, 40, 60, 80]);
+
+        assert_eq!(tree.range(41..50).next(), None);
+        assert_eq!(tree.range(90..).next(), None);
+        assert_eq!(tree.range(..20).next_back(), None);
+        assert_eq!(tree.range((Bound::Included(60), Bound::Excluded(40))).next(), None);
+
+        let empty: BinarySearchTree<i32> = BinarySearchTree::new();
+        assert_eq!(empty.range(..).next(), None);
+    }
+
+    #[test]
+    fn test_range_is_double_ended() {
+        let tree = create_test_tree(&[50, 30, 70, 20, 40, 60, 80]);
+
+        assert_eq!(tree.range(25..=70).rev().copied().collect::<Vec<_>>(), vec![70, 60, 50, 40, 30]);
+
+        let mut range = tree.range(25..75);
+        assert_eq!(range.next(), Some(&30));
+        assert_eq!(range.next_back(), Some(&70));
+        assert_eq!(range.next(), Some(&40));
+        assert_eq!(range.next_back(), Some(&60));
+        assert_eq!(range.next(), Some(&50));
+        assert_eq!(range.next_back(), None);
+        assert_eq!(range.next(), None);
+
+        let mut range = tree.range(40..=40);
+        assert_eq!(range.next_back(), Some(&40));
+        assert_eq!(range.next(), None);
+    }
+
+    // recomputes every subtree size from scratch and compares it with the stored one
+    fn assert_sizes(link: &Option<Box<Node<i32>>>) -> usize {
+        let Some(node) = link else {
+            return 0;
+        };
+
+        let counted = assert_sizes(&node.left) + assert_sizes(&node.right) + 1;
+        assert_eq!(node.size, counted, "stale size at {}", node.value);
+        counted
+    }
+
+    #[test]
+    fn test_select() {
+        let tree = create_test_tree(&[50, 30, 70, 20, 40, 60, 80]);
+
+        for (k, expected) in [20, 30, 40, 50, 60, 70, 80].iter().enumerate() {
+            assert_eq!(tree.select(k), Some(expected));
+        }
+        assert_eq!(tree.select(7), None);
+
+        let empty: BinarySearchTree<i32> = BinarySearchTree::new();
+        assert_eq!(empty.select(0), None);
+    }
+
+    #[test]
+    fn test_rank() {
+        let tree = create_test_tree(&[50, 30, 70, 20, 40, 60, 80]);
+
+        assert_eq!(tree.rank(&20), 0);
+        assert_eq!(tree.rank(&50), 3);
+        assert_eq!(tree.rank(&55), 4);
+        assert_eq!(tree.rank(&10), 0);
+        assert_eq!(tree.rank(&100), 7);
+
+        for k in 0..tree.size() {
+            assert_eq!(tree.rank(tree.select(k).unwrap()), k);
+        }
+    }
+
+    #[test]
+    fn test_count_in_range() {
+        let tree = create_test_tree(&[50, 30, 70, 20, 40, 60, 80]);
+
+        assert_eq!(tree.count_in_range(30..70), 4);
+        assert_eq!(tree.count_in_range(30..=70), 5);
+        assert_eq!(tree.count_in_range(35..65), 3);
+        assert_eq!(tree.count_in_range(..), 7);
+        assert_eq!(tree.count_in_range(..40), 2);
+        assert_eq!(tree.count_in_range(75..), 1);
+        assert_eq!(tree.count_in_range(41..50), 0);
+        assert_eq!(tree.count_in_range((Bound::Excluded(30), Bound::Excluded(60))), 2);
+        assert_eq!(tree.count_in_range((Bound::Included(60), Bound::Excluded(40))), 0);
+    }
+
+    #[test]
+    fn test_sizes_maintained_through_insert_and_remove() {
+        let mut tree = BinarySearchTree::new();
+        for i in 0..300 {
+            tree.insert((i * 37) % 101 + (i % 3) * 101);
+            tree.insert((i * 37) % 101);
+        }
+        assert_sizes(&tree.root);
+
+        for i in 0..300 {
+            tree.remove(&((i * 53) % 303));
+            if i % 25 == 0 {
+                assert_sizes(&tree.root);
+            }
+        }
+        assert_sizes(&tree.root);
+
+        let values: Vec<_> = tree.iter().copied().collect();
+        for (k, value) in values.iter().enumerate() {
+            assert_eq!(tree.select(k), Some(value));
+            assert_eq!(tree.rank(value), k);
+        }
+    }
+
+    #[test]
+    fn test_entry_vacant_insert() {
+        let mut tree = create_test_tree(&[50, 30, 70]);
+
+        match tree.entry(40) {
+            Entry::Vacant(entry) => {
+                assert_eq!(entry.get(), &40);
+                assert_eq!(entry.insert(), &40);
+            }
+            Entry::Occupied(_) => panic!("40 should be vacant"),
+        }
+
+        assert_eq!(tree.size(), 4);
+        assert_eq!(collect_in_order(&tree), vec![30, 40, 50, 70]);
+        assert_eq!(tree.rank(&40), 1);
+        assert_sizes(&tree.root);
+
+        if let Entry::Vacant(entry) = tree.entry(60) {
+            assert_eq!(entry.into_value(), 60);
+        }
+        assert_eq!(tree.size(), 4);
+        assert!(!tree.contains(&60));
+    }
+
+    #[test]
+    fn test_entry_or_insert() {
+        let mut tree = BinarySearchTree::new();
+        assert_eq!(tree.entry(5).or_insert(), &5);
+        assert_eq!(tree.entry(5).or_insert(), &5);
+        assert_eq!(tree.entry(3).get(), &3);
+        assert_eq!(tree.entry(3).or_insert(), &3);
+
+        assert_eq!(tree.size(), 2);
+        assert_eq!(collect_in_order(&tree), vec![3, 5]);
+    }
+
+    #[test]
+    fn test_entry_remove() {
+        let mut tree = create_test_tree(&[50, 30, 70, 20, 40, 60, 80]);
+
+        match tree.entry(30) {
+            Entry::Occupied(entry) => {
+                assert_eq!(entry.get(), &30);
+                assert_eq!(entry.remove_entry(), 30);
+            }
+            Entry::Vacant(_) => panic!("30 should be occupied"),
+        }
+
+        if let Entry::Occupied(entry) = tree.entry(50) {
+            entry.remove_entry();
+        }
+
+        assert_eq!(tree.size(), 5);
+        assert_eq!(collect_in_order(&tree), vec![20, 40, 60, 70, 80]);
+        assert_eq!(tree.select(1), Some(&40));
+        assert_sizes(&tree.root);
+    }
+
+    #[test]
+    fn test_from_sorted_iter() {
+        let tree = BinarySearchTree::from_sorted_iter(0..1_023);
+
+        assert_eq!(tree.size(), 1_023);
+        assert_eq!(tree.height(), 10);
+        assert!(tree.iter().copied().eq(0..1_023));
+        assert_eq!(tree.select(500), Some(&500));
+        assert_sizes(&tree.root);
+
+        let tree = BinarySearchTree::from_sorted_iter(vec![1, 2, 2, 3, 3, 3]);
+        assert_eq!(collect_in_order(&tree), vec![1, 2, 3]);
+        assert_eq!(tree.size(), 3);
+
+        let tree = BinarySearchTree::from_sorted_iter(vec![5, 1, 4, 1]);
+        assert_eq!(collect_in_order(&tree), vec![1, 4, 5]);
+
+        let empty = BinarySearchTree::<i32>::from_sorted_iter(Vec::new());
+        assert!(empty.is_empty());
+        assert_eq!(empty.height(), 0);
+    }
+
+    #[test]
+    fn test_from_sorted_iter_large() {
+        let tree = BinarySearchTree::from_sorted_iter(0..1_000_000);
+        assert_eq!(tree.size(), 1_000_000);
+        assert_eq!(tree.height(), 20);
+        assert_eq!(tree.max(), Some(&999_999));
+    }
+
+    #[test]
+    fn test_split_off() {
+        let mut tree = create_test_tree(&[50, 30, 70, 20, 40, 60, 80]);
+        let upper = tree.split_off(&45);
+
+        assert_eq!(collect_in_order(&tree), vec![20, 30, 40]);
+        assert_eq!(collect_in_order(&upper), vec![50, 60, 70, 80]);
+        assert_eq!(tree.size(), 3);
+        assert_eq!(upper.size(), 4);
+        assert_sizes(&tree.root);
+        assert_sizes(&upper.root);
+
+        let mut tree = create_test_tree(&[1, 2, 3]);
+        let upper = tree.split_off(&2);
+        assert_eq!(collect_in_order(&tree), vec![1]);
+        assert_eq!(collect_in_order(&upper), vec![2, 3]);
+
+        let upper = tree.split_off(&10);
+        assert!(upper.is_empty());
+        assert_eq!(tree.size(), 1);
+
+        let upper = tree.split_off(&0);
+        assert!(tree.is_empty());
+        assert_eq!(upper.size(), 1);
+    }
+
+    #[test]
+    fn test_append() {
+        let mut tree = create_test_tree(&[1, 3, 5, 7]);
+        let mut other = create_test_tree(&[2, 3, 6, 8, 9]);
+        tree.append(&mut other);
+
+        assert_eq!(collect_in_order(&tree), vec![1, 2, 3, 5, 6, 7, 8, 9]);
+        assert_eq!(tree.size(), 8);
+        assert!(other.is_empty());
+        assert_eq!(other.size(), 0);
+        assert_eq!(tree.height(), 4);
+        assert_sizes(&tree.root);
+
+        let mut empty = BinarySearchTree::new();
+        empty.append(&mut tree);
+        assert_eq!(empty.size(), 8);
+        assert!(tree.is_empty());
+
+        empty.append(&mut tree);
+        assert_eq!(empty.size(), 8);
+    }
+
+    #[test]
+    fn test_union_and_intersection() {
+        let a = create_test_tree(&[5, 1, 3, 7, 9]);
+        let b = create_test_tree(&[4, 2, 3, 9, 10]);
+
+        assert_eq!(a.union(&b).copied().collect::<Vec<_>>(), vec![1, 2, 3, 4, 5, 7, 9, 10]);
+        assert_eq!(a.intersection(&b).copied().collect::<Vec<_>>(), vec![3, 9]);
+        assert_eq!(b.intersection(&a).copied().collect::<Vec<_>>(), vec![3, 9]);
+
+        let empty = BinarySearchTree::new();
+        assert_eq!(a.union(&empty).count(), 5);
+        assert_eq!(a.intersection(&empty).next(), None);
+    }
+
+    #[test]
+    fn test_difference_and_symmetric_difference() {
+        let a = create_test_tree(&[5, 1, 3, 7, 9]);
+        let b = create_test_tree(&[4, 2, 3, 9, 10]);
+
+        assert_eq!(a.difference(&b).copied().collect::<Vec<_>>(), vec![1, 5, 7]);
+        assert_eq!(b.difference(&a).copied().collect::<Vec<_>>(), vec![2, 4, 10]);
+        assert_eq!(a.symmetric_difference(&b).copied().collect::<Vec<_>>(), vec![1, 2, 4, 5, 7, 10]);
+        assert_eq!(a.symmetric_difference(&a).next(), None);
+        assert_eq!(a.difference(&a).next(), None);
+    }
+
+    #[test]
+    fn test_set_operations_are_lazy() {
+        let a = BinarySearchTree::from_sorted_iter((0..1_000).map(|i| i * 2));
+        let b = BinarySearchTree::from_sorted_iter((0..1_000).map(|i| i * 3));
+
+        assert_eq!(a.intersection(&b).take(3).copied().collect::<Vec<_>>(), vec![0, 6, 12]);
+        assert_eq!(a.union(&b).nth(3), Some(&4));
+        assert_eq!(a.intersection(&b).count(), 334);
+    }
+
+    #[test]
+    fn test_subset_superset_disjoint() {
+        let small = create_test_tree(&[3, 5]);
+        let large = create_test_tree(&[1, 3, 5, 7]);
+        let other = create_test_tree(&[2, 4, 6]);
+        let empty = BinarySearchTree::new();
+
+        assert!(small.is_subset(&large));
+        assert!(!large.is_subset(&small));
+        assert!(large.is_superset(&small));
+        assert!(!small.is_superset(&large));
+        assert!(small.is_subset(&small));
+        assert!(empty.is_subset(&small));
+        assert!(!create_test_tree(&[3, 4]).is_subset(&large));
+
+        assert!(small.is_disjoint(&other));
+        assert!(!small.is_disjoint(&large));
+        assert!(empty.is_disjoint(&empty));
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut tree = create_test_tree(&[5, 3, 8]);
+        tree.clear();
+
+        assert!(tree.is_empty());
+        assert_eq!(tree.size(), 0);
+        assert!(!tree.contains(&5));
+
+        assert!(tree.insert(1));
+        assert_eq!(tree.size(), 1);
+    }
+
+    #[test]
+    fn test_degenerate_tree_does_not_overflow() {
+        let mut tree = BinarySearchTree::new();
+        for i in 0..20_000 {
+            tree.insert(i);
+        }
+        assert_eq!(tree.height(), 20_000);
+
+        let mut count = 0;
+        tree.in_order(|_| count += 1);
+        tree.post_order(|_| count += 1);
+        assert_eq!(count, 40_000);
+        assert_eq!(tree.iter().rev().count(), 20_000);
+        assert_eq!(tree.pre_order_iter().count(), 20_000);
+
+        assert_eq!(tree.remove(&0), Some(0));
+        assert_eq!(tree.max(), Some(&19_999));
+    }
+
+    #[test]
+    fn test_with_complex_types() {
+        let mut tree = BinarySearchTree::new();
+        tree.insert(String::from("pear"));
+        tree.insert(String::from("apple"));
+        tree.insert(String::from("zucchini"));
+
+        assert!(tree.contains(&String::from("apple")));
+        assert_eq!(tree.min(), Some(&String::from("apple")));
+        assert_eq!(tree.max(), Some(&String::from("zucchini")));
+        assert_eq!(tree.remove(&String::from("pear")), Some(String::from("pear")));
+        assert_eq!(tree.size(), 2);
+    }
+
+    #[test]
+    fn test_reversed_comparator() {
+        let mut tree = BinarySearchTree::with_comparator(Reversed(Natural));
+        for value in [5, 3, 7, 1, 9] {
+            assert!(tree.insert(value));
+        }
+        assert!(!tree.insert(3));
+
+        assert_eq!(tree.iter().copied().collect::<Vec<_>>(), vec![9, 7, 5, 3, 1]);
+        assert_eq!(tree.min(), Some(&9));
+        assert_eq!(tree.max(), Some(&1));
+        assert_eq!(tree.floor(&6), Some(&7));
+        assert_eq!(tree.successor(&5), Some(&3));
+        assert_eq!(tree.rank(&3), 3);
+
+        // ranges are expressed in the comparator's order, so they run from high to low
+        assert_eq!(tree.range((Bound::Included(7), Bound::Included(3))).copied().collect::<Vec<_>>(), vec![7, 5, 3]);
+        assert_eq!(tree.count_in_range((Bound::Excluded(9), Bound::Unbounded)), 4);
+
+        assert_eq!(tree.remove(&7), Some(7));
+        assert_eq!(tree.remove(&7), None);
+        assert_eq!(tree.iter().copied().collect::<Vec<_>>(), vec![9, 5, 3, 1]);
+        assert_sizes(&tree.root);
+    }
+
+    #[test]
+    fn test_closure_comparator() {
+        let mut tree = BinarySearchTree::with_comparator(|a: &String, b: &String| {
+            a.to_lowercase().cmp(&b.to_lowercase())
+        });
+        tree.insert(String::from("Banana"));
+        tree.insert(String::from("apple"));
+        tree.insert(String::from("cherry"));
+
+        assert!(!tree.insert(String::from("APPLE")));
+        assert!(tree.contains(&String::from("BANANA")));
+        assert_eq!(tree.min(), Some(&String::from("apple")));
+        assert_eq!(tree.remove(&String::from("CHERRY")), Some(String::from("cherry")));
+        assert_eq!(tree.size(), 2);
+    }
+
+    #[test]
+    fn test_comparator_by_field() {
+        #[derive(Debug, PartialEq)]
+        struct Task {
+            priority: u32,
+            name: &'static str,
+        }
+
+        let by_priority = |a: &Task, b: &Task| a.priority.cmp(&b.priority);
+        let mut tree = BinarySearchTree::with_comparator(by_priority);
+        tree.insert(Task { priority: 2, name: "write" });
+        tree.insert(Task { priority: 1, name: "plan" });
+        tree.insert(Task { priority: 3, name: "ship" });
+
+        let names: Vec<_> = tree.iter().map(|task| task.name).collect();
+        assert_eq!(names, vec!["plan", "write", "ship"]);
+
+        // only the priority takes part in the ordering, so this counts as a duplicate
+        assert!(!tree.insert(Task { priority: 2, name: "review" }));
+
+        // but an entry can swap it in for the stored one
+        if let Entry::Occupied(entry) = tree.entry(Task { priority: 2, name: "review" }) {
+            assert_eq!(entry.replace().name, "write");
+        }
+        assert_eq!(tree.size(), 3);
+
+        let probe = Task { priority: 2, name: "" };
+        assert_eq!(tree.remove(&probe).map(|task| task.name), Some("review"));
+    }
+
+    #[test]
+    fn test_comparator_bulk_operations() {
+        let mut tree = BinarySearchTree::from_sorted_iter_by(vec![1, 4, 2, 8, 4], Reversed(Natural));
+        assert_eq!(tree.iter().copied().collect::<Vec<_>>(), vec![8, 4, 2, 1]);
+
+        let upper = tree.split_off(&3);
+        assert_eq!(tree.iter().copied().collect::<Vec<_>>(), vec![8, 4]);
+        assert_eq!(upper.iter().copied().collect::<Vec<_>>(), vec![2, 1]);
+
+        let mut other = BinarySearchTree::from_sorted_iter_by(vec![5, 1], Reversed(Natural));
+        tree.append(&mut other);
+        assert_eq!(tree.iter().copied().collect::<Vec<_>>(), vec![8, 5, 4, 1]);
+        assert!(other.is_empty());
+
+        assert_eq!(tree.union(&upper).copied().collect::<Vec<_>>(), vec![8, 5, 4, 2, 1]);
+        assert_eq!(tree.intersection(&upper).copied().collect::<Vec<_>>(), vec![1]);
+        assert_eq!(tree.entry(6).or_insert(), &6);
+        assert_eq!(tree.iter().copied().collect::<Vec<_>>(), vec![8, 6, 5, 4, 1]);
+    }
+}
@@ -0,0 +1,432 @@
+use std::cmp::Ordering;
+use std::ptr;
+
+use crate::compare::{ByKey, Compare, Natural, Reversed};
+use crate::handle::{NodeHandle, Pool};
+
+// decrease_key moves values up the tree rather than nodes, so a value and its handle live in
+// an Item that the tree nodes point at, and that points back at whichever node holds it now
+struct Item<T> {
+    value: T,
+    node: *mut Node<T>,
+}
+
+struct Node<T> {
+    item: *mut Item<T>,
+    parent: *mut Node<T>,
+    child: *mut Node<T>,   // the child of highest degree; the rest follow as its siblings
+    sibling: *mut Node<T>, // next root in the root list, or next child of the parent
+    degree: usize,
+}
+
+// a heap made of binomial trees, at most one of each degree, kept in a root list sorted
+// by increasing degree. merging two heaps works like adding two binary numbers: walk both
+// root lists in step and link trees of equal degree, carrying upwards. that's O(log n) for
+// merge, push, pop and decrease_key
+pub struct BinomialHeap<T, C = Natural> {
+    head: *mut Node<T>, // the root list
+    top: *mut Node<T>,  // the root holding the greatest item under the comparator
+    size: usize,
+    items: Pool<Item<T>>,
+    nodes: Pool<Node<T>>,
+    cmp: C,
+}
+
+impl<T: Ord> BinomialHeap<T> {
+    pub fn new() -> Self {
+        Self::with_comparator(Natural)
+    }
+
+    // the same as new(), for symmetry with min()
+    pub fn max() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord> BinomialHeap<T, Reversed> {
+    // pops the smallest item first, which makes decrease_key a literal decrease
+    pub fn min() -> Self {
+        Self::with_comparator(Reversed(Natural))
+    }
+}
+
+impl<T, F> BinomialHeap<T, ByKey<F>> {
+    pub fn with_key<K: Ord>(key: F) -> Self
+    where
+        F: Fn(&T) -> K,
+    {
+        Self::with_comparator(ByKey(key))
+    }
+}
+
+impl<T, F> BinomialHeap<T, Reversed<ByKey<F>>> {
+    pub fn min_by_key<K: Ord>(key: F) -> Self
+    where
+        F: Fn(&T) -> K,
+    {
+        Self::with_comparator(Reversed(ByKey(key)))
+    }
+}
+
+impl<T, C> BinomialHeap<T, C> {
+    pub fn with_comparator(cmp: C) -> Self {
+        BinomialHeap {
+            head: ptr::null_mut(),
+            top: ptr::null_mut(),
+            size: 0,
+            items: Pool::new(),
+            nodes: Pool::new(),
+            cmp,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    pub fn peek(&self) -> Option<&T> {
+        if self.top.is_null() {
+            return None;
+        }
+
+        unsafe { Some(&(*(*self.top).item).value) }
+    }
+
+    pub fn get(&self, handle: &NodeHandle) -> Option<&T> {
+        let item = self.items.resolve(handle)?;
+        unsafe { Some(&(*item).value) }
+    }
+
+    // false once the item has been popped or removed, or if it's in another heap
+    pub fn contains(&self, handle: &NodeHandle) -> bool {
+        self.items.resolve(handle).is_some()
+    }
+
+    fn value(&self, node: *mut Node<T>) -> &T {
+        unsafe { &(*(*node).item).value }
+    }
+}
+
+impl<T, C: Compare<T>> BinomialHeap<T, C> {
+    pub fn push(&mut self, value: T) -> NodeHandle {
+        let item = self.items.alloc(Item {
+            value,
+            node: ptr::null_mut(),
+        });
+        let node = self.nodes.alloc(Node {
+            item,
+            parent: ptr::null_mut(),
+            child: ptr::null_mut(),
+            sibling: ptr::null_mut(),
+            degree: 0,
+        });
+        unsafe { (*item).node = node };
+
+        self.head = self.union(self.head, node);
+        self.size += 1;
+        self.update_top();
+        self.items.handle(item)
+    }
+
+    // removes and returns the greatest item under the comparator
+    pub fn pop(&mut self) -> Option<T> {
+        if self.top.is_null() {
+            return None;
+        }
+
+        Some(self.remove_root(self.top))
+    }
+
+    // takes every item of `other`. handles into `other` carry on working in this heap
+    pub fn merge(&mut self, mut other: BinomialHeap<T, C>) {
+        self.items.absorb(&mut other.items);
+        self.nodes.absorb(&mut other.nodes);
+        self.head = self.union(self.head, other.head);
+        self.size += other.size;
+        self.update_top();
+    }
+
+    // moves the item towards the top by replacing its value, and returns the old one, or None
+    // for a stale handle. the name is from min-heaps, where that means lowering the value; in
+    // general it panics if the old value is greater than `value` under the comparator
+    pub fn decrease_key(&mut self, handle: &NodeHandle, value: T) -> Option<T> {
+        let item = self.items.resolve(handle)?;
+        unsafe {
+            assert!(!self.greater(&(*item).value, &value), "decrease_key can't move an item away from the top");
+            let old = std::mem::replace(&mut (*item).value, value);
+            self.bubble_up((*item).node, false);
+            self.update_top();
+            Some(old)
+        }
+    }
+
+    pub fn remove(&mut self, handle: &NodeHandle) -> Option<T> {
+        let item = self.items.resolve(handle)?;
+        // float the item all the way up, as though it were ahead of everything
+        let root = self.bubble_up(unsafe { (*item).node }, true);
+        Some(self.remove_root(root))
+    }
+
+    // swaps the node's item with its parent's while it's greater (or always, with `force`),
+    // and returns the node the item ends up in
+    fn bubble_up(&mut self, mut node: *mut Node<T>, force: bool) -> *mut Node<T> {
+        unsafe {
+            while !(*node).parent.is_null() {
+                let parent = (*node).parent;
+                if !force && !self.greater(self.value(node), self.value(parent)) {
+                    break;
+                }
+                std::mem::swap(&mut (*node).item, &mut (*parent).item);
+                (*(*node).item).node = node;
+                (*(*parent).item).node = parent;
+                node = parent;
+            }
+        }
+        node
+    }
+
+    fn remove_root(&mut self, root: *mut Node<T>) -> T {
+        unsafe {
+            // unlink the root from the root list
+            if self.head == root {
+                self.head = (*root).sibling;
+            } else {
+                let mut prev = self.head;
+                while (*prev).sibling != root {
+                    prev = (*prev).sibling;
+                }
+                (*prev).sibling = (*root).sibling;
+            }
+
+            // its children form a root list of their own, once reversed into increasing degree
+            let mut children = ptr::null_mut();
+            let mut child = (*root).child;
+            while !child.is_null() {
+                let next = (*child).sibling;
+                (*child).parent = ptr::null_mut();
+                (*child).sibling = children;
+                children = child;
+                child = next;
+            }
+
+            self.head = self.union(self.head, children);
+            self.size -= 1;
+            self.update_top();
+
+            let item = (*root).item;
+            self.nodes.free(root);
+            self.items.free(item).value
+        }
+    }
+
+    // merges two root lists into one with at most one tree per degree
+    fn union(&mut self, a: *mut Node<T>, b: *mut Node<T>) -> *mut Node<T> {
+        let mut head = self.merge_lists(a, b);
+        if head.is_null() {
+            return head;
+        }
+
+        unsafe {
+            let mut prev: *mut Node<T> = ptr::null_mut();
+            let mut current = head;
+            let mut next = (*current).sibling;
+            while !next.is_null() {
+                let next_next = (*next).sibling;
+                if (*current).degree != (*next).degree
+                    || (!next_next.is_null() && (*next_next).degree == (*current).degree)
+                {
+                    // nothing to link here, or three trees of one degree in a row, in which
+                    // case the last two get linked on the next step
+                    prev = current;
+                    current = next;
+                } else if !self.greater(self.value(next), self.value(current)) {
+                    (*current).sibling = next_next;
+                    self.link(next, current);
+                } else {
+                    if prev.is_null() {
+                        head = next;
+                    } else {
+                        (*prev).sibling = next;
+                    }
+                    self.link(current, next);
+                    current = next;
+                }
+                next = (*current).sibling;
+            }
+        }
+        head
+    }
+
+    // interleaves two degree-sorted root lists
+    fn merge_lists(&mut self, mut a: *mut Node<T>, mut b: *mut Node<T>) -> *mut Node<T> {
+        let mut head = ptr::null_mut();
+        let mut tail: *mut Node<T> = ptr::null_mut();
+        unsafe {
+            while !a.is_null() || !b.is_null() {
+                let take_a = b.is_null() || (!a.is_null() && (*a).degree <= (*b).degree);
+                let next = if take_a { a } else { b };
+                if take_a {
+                    a = (*a).sibling;
+                } else {
+                    b = (*b).sibling;
+                }
+
+                if tail.is_null() {
+                    head = next;
+                } else {
+                    (*tail).sibling = next;
+                }
+                tail = next;
+            }
+        }
+        head
+    }
+
+    // makes `child` the first child of `parent`; both are roots of equal degree
+    fn link(&mut self, child: *mut Node<T>, parent: *mut Node<T>) {
+        unsafe {
+            (*child).parent = parent;
+            (*child).sibling = (*parent).child;
+            (*parent).child = child;
+            (*parent).degree += 1;
+        }
+    }
+
+    fn update_top(&mut self) {
+        self.top = ptr::null_mut();
+        let mut root = self.head;
+        while !root.is_null() {
+            if self.top.is_null() || self.greater(self.value(root), self.value(self.top)) {
+                self.top = root;
+            }
+            root = unsafe { (*root).sibling };
+        }
+    }
+
+    fn greater(&self, a: &T, b: &T) -> bool {
+        self.cmp.compare(a, b) == Ordering::Greater
+    }
+}
+
+impl<T, C: Default> Default for BinomialHeap<T, C> {
+    fn default() -> Self {
+        Self::with_comparator(C::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the root list has strictly increasing degrees, every tree of degree k is a binomial tree
+    // with 2^k nodes in heap order, and every item points back at its node
+    fn assert_well_formed<T, C: Compare<T>>(heap: &BinomialHeap<T, C>) {
+        fn check<T, C: Compare<T>>(heap: &BinomialHeap<T, C>, node: *mut Node<T>) -> usize {
+            unsafe {
+                assert!((*(*node).item).node == node);
+                let mut count = 1;
+                let mut expected_degree = (*node).degree;
+                let mut child = (*node).child;
+                while !child.is_null() {
+                    // children have degrees k-1, k-2, ..., 0
+                    expected_degree -= 1;
+                    assert_eq!((*child).degree, expected_degree);
+                    assert!((*child).parent == node);
+                    assert!(!heap.greater(heap.value(child), heap.value(node)));
+                    count += check(heap, child);
+                    child = (*child).sibling;
+                }
+                assert_eq!(expected_degree, 0);
+                assert_eq!(count, 1 << (*node).degree);
+                count
+            }
+        }
+
+        let mut count = 0;
+        let mut trees = 0;
+        let mut previous_degree = None;
+        let mut root = heap.head;
+        while !root.is_null() {
+            unsafe {
+                assert!((*root).parent.is_null());
+                assert!(previous_degree.is_none_or(|degree| degree < (*root).degree));
+                assert!(!heap.greater(heap.value(root), heap.value(heap.top)));
+                previous_degree = Some((*root).degree);
+                count += check(heap, root);
+                trees += 1;
+                root = (*root).sibling;
+            }
+        }
+        assert_eq!(count, heap.size);
+        // one tree per set bit of the size
+        assert_eq!(trees, heap.size.count_ones());
+    }
+
+    #[test]
+    fn test_structure_through_operations() {
+        let mut heap = BinomialHeap::min();
+        let handles: Vec<NodeHandle> = (0..200).map(|i| heap.push((i * 37) % 101)).collect();
+        assert_well_formed(&heap);
+
+        for (i, handle) in handles.iter().enumerate().step_by(7) {
+            heap.decrease_key(handle, -(i as i32));
+            assert_well_formed(&heap);
+        }
+        for handle in handles.iter().skip(3).step_by(11) {
+            heap.remove(handle);
+            assert_well_formed(&heap);
+        }
+        for _ in 0..50 {
+            heap.pop();
+            assert_well_formed(&heap);
+        }
+    }
+
+    #[test]
+    fn test_merge_adds_like_binary_numbers() {
+        let mut a = BinomialHeap::min();
+        let mut b = BinomialHeap::min();
+        for i in 0..13 {
+            a.push(i);
+        }
+        for i in 0..6 {
+            b.push(i + 100);
+        }
+
+        a.merge(b);
+        assert_well_formed(&a);
+        assert_eq!(a.size(), 19);
+        assert_eq!(a.peek(), Some(&0));
+    }
+
+    #[test]
+    fn test_comparators() {
+        let mut heap = BinomialHeap::new();
+        let handles: Vec<NodeHandle> = (0..10).map(|value| heap.push(value)).collect();
+        // with the natural order decrease_key raises the value
+        heap.decrease_key(&handles[2], 20);
+        assert_well_formed(&heap);
+        assert_eq!(heap.pop(), Some(20));
+        assert_eq!(heap.pop(), Some(9));
+
+        let mut words = BinomialHeap::with_key(|word: &&str| word.len());
+        for word in ["a", "abcd", "ab", "abc"] {
+            words.push(word);
+        }
+        assert_well_formed(&words);
+        assert_eq!(words.pop(), Some("abcd"));
+        assert_eq!(words.peek(), Some(&"abc"));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_decrease_key_rejects_increase() {
+        let mut heap = BinomialHeap::min();
+        let handle = heap.push(5);
+        heap.decrease_key(&handle, 6);
+    }
+}
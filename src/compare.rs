@@ -0,0 +1,84 @@
+use std::cmp::Ordering;
+
+// an ordering that lives in a value instead of in the element type, so the ordered collections
+// can sort the same type several ways without newtype wrappers
+pub trait Compare<T: ?Sized> {
+    fn compare(&self, a: &T, b: &T) -> Ordering;
+}
+
+// the element type's own Ord
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Natural;
+
+impl<T: Ord + ?Sized> Compare<T> for Natural {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        a.cmp(b)
+    }
+}
+
+// flips another comparator, e.g. Reversed(Natural) sorts largest first
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Reversed<C = Natural>(pub C);
+
+impl<T: ?Sized, C: Compare<T>> Compare<T> for Reversed<C> {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        self.0.compare(b, a)
+    }
+}
+
+// orders by a key derived from each element, e.g. ByKey(|job: &Job| job.deadline)
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ByKey<F>(pub F);
+
+impl<T: ?Sized, K: Ord, F> Compare<T> for ByKey<F>
+where
+    F: Fn(&T) -> K,
+{
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        self.0(a).cmp(&self.0(b))
+    }
+}
+
+// any closure of the right shape works as a comparator
+impl<T: ?Sized, F> Compare<T> for F
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        self(a, b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_natural() {
+        assert_eq!(Natural.compare(&1, &2), Ordering::Less);
+        assert_eq!(Natural.compare(&2, &2), Ordering::Equal);
+        assert_eq!(Natural.compare("b", "a"), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_reversed() {
+        assert_eq!(Reversed(Natural).compare(&1, &2), Ordering::Greater);
+        assert_eq!(Reversed(Natural).compare(&2, &2), Ordering::Equal);
+        assert_eq!(Reversed(Reversed(Natural)).compare(&1, &2), Ordering::Less);
+    }
+
+    #[test]
+    fn test_closures() {
+        let by_length = |a: &String, b: &String| a.len().cmp(&b.len());
+        assert_eq!(by_length.compare(&String::from("aaa"), &String::from("b")), Ordering::Greater);
+        assert_eq!(Reversed(by_length).compare(&String::from("aaa"), &String::from("b")), Ordering::Less);
+    }
+
+    #[test]
+    fn test_by_key() {
+        let by_length = ByKey(|s: &str| s.len());
+        assert_eq!(by_length.compare("aaa", "b"), Ordering::Greater);
+        assert_eq!(by_length.compare("ab", "cd"), Ordering::Equal);
+        assert_eq!(Reversed(by_length).compare("aaa", "b"), Ordering::Less);
+    }
+}
@@ -0,0 +1,513 @@
+use std::borrow::Borrow;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash};
+use std::mem;
+
+use crate::hasher::SipBuildHasher;
+
+const MIN_SLOTS: usize = 8; // per table
+const STASH_SIZE: usize = 4;
+// failed rebuilds at one size before the tables are doubled instead
+const REBUILDS_PER_SIZE: usize = 4;
+
+type Slot<K, V> = Option<(K, V)>;
+
+// cuckoo hashing: every key has exactly one candidate slot in each of the d tables, each
+// table with its own independently seeded hash function, and lives in one of them or in a
+// small stash. a lookup therefore checks at most d slots plus the stash, however full or
+// unlucky the map is. the work moves to insertion, which evicts ("kicks") occupants to
+// their other candidate slots until something lands in a free one
+pub struct CuckooMap<K, V> {
+    tables: Vec<Vec<Slot<K, V>>>,
+    hashers: Vec<SipBuildHasher>,
+    stash: Vec<(K, V)>, // entries no table had room for, at most STASH_SIZE of them
+    slots: usize,       // per table, always a power of two
+    len: usize,
+    seed: u64, // where the next round of hash seeds comes from
+    rebuilds: usize,
+}
+
+impl<K, V> CuckooMap<K, V> {
+    pub fn new() -> Self {
+        Self::with_tables(2)
+    }
+
+    // d-ary cuckoo hashing: more tables means more candidate slots per key, which lets the
+    // map run much fuller (80% rather than 50%) at the cost of more slots per lookup.
+    // panics if `tables` is less than 2
+    pub fn with_tables(tables: usize) -> Self {
+        Self::with_tables_and_seed(tables, RandomState::new().hash_one(0u64))
+    }
+
+    // the same seed always gives the same hash functions, and the same sequence of them
+    // across rebuilds
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_tables_and_seed(2, seed)
+    }
+
+    pub fn with_tables_and_seed(tables: usize, seed: u64) -> Self {
+        assert!(tables >= 2, "cuckoo hashing needs at least two tables");
+        let mut map = CuckooMap {
+            tables: Vec::new(),
+            hashers: Vec::new(),
+            stash: Vec::new(),
+            slots: MIN_SLOTS,
+            len: 0,
+            seed,
+            rebuilds: 0,
+        };
+        map.hashers = (0..tables).map(|_| map.next_hasher()).collect();
+        map.tables = (0..tables).map(|_| empty_table(MIN_SLOTS)).collect();
+        map
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn tables(&self) -> usize {
+        self.tables.len()
+    }
+
+    // total slots across all tables, not counting the stash
+    pub fn capacity(&self) -> usize {
+        self.tables.len() * self.slots
+    }
+
+    // how many times the map has been rebuilt, for growth or with fresh hash functions
+    pub fn rebuilds(&self) -> usize {
+        self.rebuilds
+    }
+
+    pub fn clear(&mut self) {
+        for table in &mut self.tables {
+            table.iter_mut().for_each(|slot| *slot = None);
+        }
+        self.stash.clear();
+        self.len = 0;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        let tables = self.tables.iter().flatten().flatten();
+        tables.chain(&self.stash).map(|(key, value)| (key, value))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.iter().map(|(_, value)| value)
+    }
+
+    fn next_hasher(&mut self) -> SipBuildHasher {
+        // SipBuildHasher::new mixes the seed, so consecutive seeds give unrelated keys
+        self.seed = self.seed.wrapping_add(1);
+        SipBuildHasher::new(self.seed)
+    }
+
+    // growing past this many entries would leave too little slack for insertions to succeed
+    fn max_len(&self) -> usize {
+        if self.tables.len() == 2 {
+            self.capacity() / 2
+        } else {
+            self.capacity() / 5 * 4
+        }
+    }
+
+    // kick chains longer than this are taken as a sign of a cycle. a random walk needs
+    // O(log n) kicks on average, so this rarely cuts a viable insertion short
+    fn max_kicks(&self) -> usize {
+        8 * (self.slots.trailing_zeros() as usize + 1)
+    }
+}
+
+impl<K: Hash + Eq, V> CuckooMap<K, V> {
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if let Some(current) = self.get_mut(&key) {
+            return Some(mem::replace(current, value));
+        }
+
+        if self.len + 1 > self.max_len() {
+            self.rebuild(self.slots * 2);
+        }
+        self.len += 1;
+        if let Err(homeless) = self.place((key, value)) {
+            // the stash is allowed to overflow only until the rebuild picks everything up
+            self.stash.push(homeless);
+            if self.stash.len() > STASH_SIZE {
+                self.rebuild(self.slots);
+            }
+        }
+        None
+    }
+
+    // at most one slot per table, then the stash
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        for (table, hasher) in self.tables.iter().zip(&self.hashers) {
+            if let Some((k, v)) = &table[self.index(hasher, key)]
+                && k.borrow() == key
+            {
+                return Some(v);
+            }
+        }
+        self.stash.iter().find(|(k, _)| k.borrow() == key).map(|(_, v)| v)
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let (table, index) = self.find(key)?;
+        match table {
+            Some(table) => self.tables[table][index].as_mut().map(|(_, v)| v),
+            None => Some(&mut self.stash[index].1),
+        }
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.find(key).is_some()
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let (table, index) = self.find(key)?;
+        let (_, value) = match table {
+            Some(table) => self.tables[table][index].take()?,
+            None => self.stash.swap_remove(index),
+        };
+        self.len -= 1;
+
+        // the freed slot may be one a stashed entry was waiting for
+        if table.is_some() {
+            let stash = mem::take(&mut self.stash);
+            for entry in stash {
+                if let Err(entry) = self.place_without_kicking(entry) {
+                    self.stash.push(entry);
+                }
+            }
+        }
+        Some(value)
+    }
+
+    // Some(table) and the slot within it, or None and the position in the stash
+    fn find<Q>(&self, key: &Q) -> Option<(Option<usize>, usize)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        for (table, hasher) in self.hashers.iter().enumerate() {
+            let index = self.index(hasher, key);
+            if let Some((k, _)) = &self.tables[table][index]
+                && k.borrow() == key
+            {
+                return Some((Some(table), index));
+            }
+        }
+        let position = self.stash.iter().position(|(k, _)| k.borrow() == key)?;
+        Some((None, position))
+    }
+
+    fn index<Q: Hash + ?Sized>(&self, hasher: &SipBuildHasher, key: &Q) -> usize {
+        hasher.hash_one(key) as usize & (self.slots - 1)
+    }
+
+    fn place_without_kicking(&mut self, entry: (K, V)) -> Result<(), (K, V)> {
+        for table in 0..self.tables.len() {
+            let index = self.index(&self.hashers[table], &entry.0);
+            if self.tables[table][index].is_none() {
+                self.tables[table][index] = Some(entry);
+                return Ok(());
+            }
+        }
+        Err(entry)
+    }
+
+    // puts the entry into the tables, kicking occupants along to their next table as needed.
+    // hands back whichever entry is left without a slot when the kick budget runs out
+    fn place(&mut self, entry: (K, V)) -> Result<(), (K, V)> {
+        let mut entry = match self.place_without_kicking(entry) {
+            Ok(()) => return Ok(()),
+            Err(entry) => entry,
+        };
+
+        // the entry in hand always evicts the occupant of its slot in `table`, and the evicted
+        // entry then tries the next table along
+        let mut table = 0;
+        for _ in 0..self.max_kicks() {
+            let index = self.index(&self.hashers[table], &entry.0);
+            let evicted = self.tables[table][index].replace(entry);
+            let evicted = evicted.expect("kicked an entry out of an empty slot");
+            entry = match self.place_without_kicking(evicted) {
+                Ok(()) => return Ok(()),
+                Err(evicted) => evicted,
+            };
+            table = (table + 1) % self.tables.len();
+        }
+        Err(entry)
+    }
+
+    // reinserts everything into fresh tables of `slots` slots each, hashed with new seeds.
+    // if that fails too, it tries again with another set of seeds, and after a few failures
+    // at one size with twice as many slots
+    fn rebuild(&mut self, mut slots: usize) {
+        let mut entries: Vec<(K, V)> = mem::take(&mut self.stash);
+        let mut failures = 0;
+        'attempt: loop {
+            for table in &mut self.tables {
+                entries.extend(table.drain(..).flatten());
+            }
+            entries.append(&mut self.stash);
+
+            self.rebuilds += 1;
+            self.slots = slots;
+            for table in 0..self.tables.len() {
+                self.hashers[table] = self.next_hasher();
+                self.tables[table] = empty_table(slots);
+            }
+
+            while let Some(entry) = entries.pop() {
+                if let Err(homeless) = self.place(entry) {
+                    if self.stash.len() < STASH_SIZE {
+                        self.stash.push(homeless);
+                        continue;
+                    }
+
+                    entries.push(homeless);
+                    failures += 1;
+                    if failures % REBUILDS_PER_SIZE == 0 {
+                        // no seed can separate keys whose hashes are equal for every seed
+                        assert!(
+                            slots < 64 * (self.len + MIN_SLOTS),
+                            "keys collide under every hash function; is their Hash consistent with Eq?"
+                        );
+                        slots *= 2;
+                    }
+                    continue 'attempt;
+                }
+            }
+            return;
+        }
+    }
+}
+
+impl<K, V> Default for CuckooMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + Eq, V> FromIterator<(K, V)> for CuckooMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = CuckooMap::new();
+        for (key, value) in iter {
+            map.insert(key, value);
+        }
+        map
+    }
+}
+
+fn empty_table<K, V>(slots: usize) -> Vec<Slot<K, V>> {
+    (0..slots).map(|_| None).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap as StdHashMap;
+    use std::hash::Hasher;
+
+    // every entry is at one of its candidate slots or in the stash, and the stash is bounded
+    fn assert_cuckoo_invariant<K: Hash + Eq, V>(map: &CuckooMap<K, V>) {
+        let mut count = map.stash.len();
+        assert!(map.stash.len() <= STASH_SIZE);
+        for (table, slots) in map.tables.iter().enumerate() {
+            assert_eq!(slots.len(), map.slots);
+            for (index, slot) in slots.iter().enumerate() {
+                if let Some((key, _)) = slot {
+                    assert_eq!(map.index(&map.hashers[table], key), index);
+                    count += 1;
+                }
+            }
+        }
+        assert_eq!(count, map.len());
+        assert!(map.len() <= map.max_len());
+    }
+
+    fn check_against_std(tables: usize) {
+        let mut map = CuckooMap::with_tables_and_seed(tables, 7);
+        let mut expected = StdHashMap::new();
+        let mut state = 0x9e37_79b9u32;
+        for step in 0..20_000u32 {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            let key = state % 3_000;
+
+            if state.is_multiple_of(4) {
+                assert_eq!(map.remove(&key), expected.remove(&key));
+            } else {
+                assert_eq!(map.insert(key, step), expected.insert(key, step));
+            }
+            assert_eq!(map.len(), expected.len());
+        }
+
+        assert_cuckoo_invariant(&map);
+        for key in 0..3_000 {
+            assert_eq!(map.get(&key), expected.get(&key));
+        }
+    }
+
+    #[test]
+    fn test_basic_operations() {
+        let mut map = CuckooMap::new();
+        assert!(map.is_empty());
+        assert_eq!(map.tables(), 2);
+
+        assert_eq!(map.insert("one", 1), None);
+        assert_eq!(map.insert("two", 2), None);
+        assert_eq!(map.insert("one", 10), Some(1));
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get("one"), Some(&10));
+        assert_eq!(map.get("three"), None);
+
+        *map.get_mut("two").unwrap() += 1;
+        assert_eq!(map.get("two"), Some(&3));
+        assert!(map.contains_key("two"));
+
+        assert_eq!(map.remove("two"), Some(3));
+        assert_eq!(map.remove("two"), None);
+        assert!(!map.contains_key("two"));
+        assert_eq!(map.len(), 1);
+
+        map.clear();
+        assert!(map.is_empty());
+        assert_eq!(map.get("one"), None);
+    }
+
+    #[test]
+    fn test_matches_std_two_tables() {
+        check_against_std(2);
+    }
+
+    #[test]
+    fn test_matches_std_d_ary() {
+        check_against_std(3);
+        check_against_std(4);
+    }
+
+    #[test]
+    fn test_growth_keeps_every_entry_reachable() {
+        let mut map = CuckooMap::with_seed(1);
+        for key in 0..50_000u64 {
+            map.insert(key, key * 2);
+        }
+
+        assert_cuckoo_invariant(&map);
+        assert!(map.rebuilds() > 0);
+        assert!((0..50_000u64).all(|key| map.get(&key) == Some(&(key * 2))));
+        assert_eq!(map.get(&50_000), None);
+    }
+
+    #[test]
+    fn test_more_tables_run_fuller() {
+        let two = (0..10_000).map(|key| (key, ())).collect::<CuckooMap<_, _>>();
+        let mut three = CuckooMap::with_tables_and_seed(3, 3);
+        for key in 0..10_000 {
+            three.insert(key, ());
+        }
+
+        assert!(two.len() * 2 <= two.capacity());
+        assert!(three.capacity() < two.capacity());
+        assert_cuckoo_invariant(&three);
+    }
+
+    #[test]
+    fn test_same_seed_same_layout() {
+        let build = |seed| {
+            let mut map = CuckooMap::with_seed(seed);
+            for key in 0..1_000 {
+                map.insert(key, ());
+            }
+            map.tables
+                .iter()
+                .map(|table| table.iter().map(|slot| slot.as_ref().map(|(k, _)| *k)).collect())
+                .collect::<Vec<Vec<_>>>()
+        };
+        assert_eq!(build(5), build(5));
+        assert_ne!(build(5), build(6));
+    }
+
+    #[test]
+    fn test_stash_absorbs_failures() {
+        // keys whose hash is one of only three values, whatever the seed: with two tables each
+        // value has just two candidate slots, so at most 6 keys fit in the tables and the rest
+        // must go to the stash
+        #[derive(PartialEq, Eq)]
+        struct Clumped(u32);
+
+        impl Hash for Clumped {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                (self.0 % 3).hash(state)
+            }
+        }
+
+        let mut map = CuckooMap::with_seed(11);
+        for key in 0..8 {
+            map.insert(Clumped(key), key);
+        }
+
+        assert_eq!(map.len(), 8);
+        assert!(!map.stash.is_empty());
+        assert_cuckoo_invariant(&map);
+        assert!((0..8).all(|key| map.get(&Clumped(key)) == Some(&key)));
+
+        // freeing one of a stashed key's candidate slots lets it move out of the stash
+        let stashed = map.stash.len();
+        let class = map.stash[0].0.0 % 3;
+        let in_tables = map.tables.iter().flatten().flatten().find(|(k, _)| k.0 % 3 == class);
+        let in_tables = in_tables.unwrap().0.0;
+        map.remove(&Clumped(in_tables));
+        assert_eq!(map.stash.len(), stashed - 1);
+        assert_cuckoo_invariant(&map);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_inseparable_keys_panic() {
+        // every key hashes the same under every seed, so no number of rebuilds can help
+        #[derive(PartialEq, Eq)]
+        struct Same(u32);
+
+        impl Hash for Same {
+            fn hash<H: Hasher>(&self, _: &mut H) {}
+        }
+
+        let mut map = CuckooMap::with_seed(0);
+        for key in 0..100 {
+            map.insert(Same(key), ());
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_one_table_panics() {
+        CuckooMap::<i32, i32>::with_tables(1);
+    }
+}
@@ -44,6 +44,12 @@ impl<T> Deque<T> {
     }
 }
 
+impl<T> Default for Deque<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -134,6 +134,12 @@ impl<T> DoublyLinkedList<T> {
     }
 }
 
+impl<T> Default for DoublyLinkedList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T> Drop for DoublyLinkedList<T> { // the drop trait is like a c++ destructor
     fn drop(&mut self) {
         while !self.is_empty() {
@@ -0,0 +1,567 @@
+use std::cmp::Ordering;
+use std::ptr;
+
+use crate::compare::{ByKey, Compare, Natural, Reversed};
+use crate::handle::{NodeHandle, Pool};
+
+// siblings, and the trees of the root list, form circular doubly linked lists
+struct Node<T> {
+    value: T,
+    parent: *mut Node<T>,
+    child: *mut Node<T>, // any one of the children
+    left: *mut Node<T>,
+    right: *mut Node<T>,
+    degree: usize,
+    marked: bool, // lost a child since it last became a child itself
+}
+
+// a heap that puts off all restructuring until pop: push and merge just add trees to the
+// root list, and decrease_key cuts the node loose into the root list. pop then consolidates
+// the roots until no two share a degree. the cascading cuts keep every subtree of degree k
+// at least F(k + 2) nodes big, which bounds degrees by log_phi(n) and gives amortized O(1)
+// push, merge and decrease_key, and O(log n) pop and delete. like PriorityQueue it's a
+// max-heap with the natural order and a min-heap with min()
+pub struct FibonacciHeap<T, C = Natural> {
+    top: *mut Node<T>, // the greatest root under the comparator; the root list is reached through it
+    size: usize,
+    pool: Pool<Node<T>>,
+    cmp: C,
+}
+
+impl<T: Ord> FibonacciHeap<T> {
+    pub fn new() -> Self {
+        Self::with_comparator(Natural)
+    }
+
+    // the same as new(), for symmetry with min()
+    pub fn max() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord> FibonacciHeap<T, Reversed> {
+    // pops the smallest item first, which makes decrease_key a literal decrease
+    pub fn min() -> Self {
+        Self::with_comparator(Reversed(Natural))
+    }
+}
+
+impl<T, F> FibonacciHeap<T, ByKey<F>> {
+    pub fn with_key<K: Ord>(key: F) -> Self
+    where
+        F: Fn(&T) -> K,
+    {
+        Self::with_comparator(ByKey(key))
+    }
+}
+
+impl<T, F> FibonacciHeap<T, Reversed<ByKey<F>>> {
+    pub fn min_by_key<K: Ord>(key: F) -> Self
+    where
+        F: Fn(&T) -> K,
+    {
+        Self::with_comparator(Reversed(ByKey(key)))
+    }
+}
+
+impl<T, C> FibonacciHeap<T, C> {
+    pub fn with_comparator(cmp: C) -> Self {
+        FibonacciHeap {
+            top: ptr::null_mut(),
+            size: 0,
+            pool: Pool::new(),
+            cmp,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    pub fn peek(&self) -> Option<&T> {
+        if self.top.is_null() {
+            return None;
+        }
+
+        unsafe { Some(&(*self.top).value) }
+    }
+
+    pub fn get(&self, handle: &NodeHandle) -> Option<&T> {
+        let node = self.pool.resolve(handle)?;
+        unsafe { Some(&(*node).value) }
+    }
+
+    // false once the item has been popped or deleted, or if it's in another heap
+    pub fn contains(&self, handle: &NodeHandle) -> bool {
+        self.pool.resolve(handle).is_some()
+    }
+
+    fn value(&self, node: *mut Node<T>) -> &T {
+        unsafe { &(*node).value }
+    }
+}
+
+impl<T, C: Compare<T>> FibonacciHeap<T, C> {
+    pub fn push(&mut self, value: T) -> NodeHandle {
+        let node = self.pool.alloc(Node {
+            value,
+            parent: ptr::null_mut(),
+            child: ptr::null_mut(),
+            left: ptr::null_mut(),
+            right: ptr::null_mut(),
+            degree: 0,
+            marked: false,
+        });
+        unsafe {
+            (*node).left = node;
+            (*node).right = node;
+        }
+        self.add_root(node);
+        self.size += 1;
+        self.pool.handle(node)
+    }
+
+    // removes and returns the greatest item under the comparator, then consolidates the root list
+    pub fn pop(&mut self) -> Option<T> {
+        if self.top.is_null() {
+            return None;
+        }
+
+        let top = self.top;
+        unsafe {
+            // the children join the root list
+            let child = (*top).child;
+            if !child.is_null() {
+                let mut current = child;
+                loop {
+                    (*current).parent = ptr::null_mut();
+                    current = (*current).right;
+                    if current == child {
+                        break;
+                    }
+                }
+                splice(top, child);
+                (*top).child = ptr::null_mut();
+            }
+
+            if (*top).right == top {
+                self.top = ptr::null_mut();
+            } else {
+                self.top = (*top).right;
+                unlink(top);
+                self.consolidate();
+            }
+        }
+        self.size -= 1;
+        Some(self.pool.free(top).value)
+    }
+
+    // takes every item of `other`. handles into `other` carry on working in this heap
+    pub fn merge(&mut self, mut other: FibonacciHeap<T, C>) {
+        self.pool.absorb(&mut other.pool);
+        self.size += other.size;
+        if other.top.is_null() {
+            return;
+        }
+
+        if self.top.is_null() {
+            self.top = other.top;
+        } else {
+            unsafe { splice(self.top, other.top) };
+            if self.greater(self.value(other.top), self.value(self.top)) {
+                self.top = other.top;
+            }
+        }
+    }
+
+    // moves the item towards the top by replacing its value, and returns the old one, or None
+    // for a stale handle. the name is from min-heaps, where that means lowering the value; in
+    // general it panics if the old value is greater than `value` under the comparator
+    pub fn decrease_key(&mut self, handle: &NodeHandle, value: T) -> Option<T> {
+        let node = self.pool.resolve(handle)?;
+        unsafe {
+            assert!(!self.greater(&(*node).value, &value), "decrease_key can't move an item away from the top");
+            let old = std::mem::replace(&mut (*node).value, value);
+
+            let parent = (*node).parent;
+            if !parent.is_null() && self.greater(self.value(node), self.value(parent)) {
+                self.cut(node, parent);
+                self.cascading_cut(parent);
+            }
+            if self.greater(self.value(node), self.value(self.top)) {
+                self.top = node;
+            }
+            Some(old)
+        }
+    }
+
+    pub fn delete(&mut self, handle: &NodeHandle) -> Option<T> {
+        let node = self.pool.resolve(handle)?;
+        // what decreasing the key past everything else would do: cut the node loose and treat
+        // it as the top, whatever its value
+        unsafe {
+            let parent = (*node).parent;
+            if !parent.is_null() {
+                self.cut(node, parent);
+                self.cascading_cut(parent);
+            }
+        }
+        self.top = node;
+        self.pop()
+    }
+
+    // adds a lone node or a detached tree to the root list
+    fn add_root(&mut self, node: *mut Node<T>) {
+        if self.top.is_null() {
+            self.top = node;
+            return;
+        }
+
+        unsafe { splice(self.top, node) };
+        if self.greater(self.value(node), self.value(self.top)) {
+            self.top = node;
+        }
+    }
+
+    // moves `node` from its parent's children to the root list
+    fn cut(&mut self, node: *mut Node<T>, parent: *mut Node<T>) {
+        unsafe {
+            if (*parent).child == node {
+                (*parent).child = if (*node).right == node { ptr::null_mut() } else { (*node).right };
+            }
+            unlink(node);
+            (*parent).degree -= 1;
+            (*node).parent = ptr::null_mut();
+            (*node).marked = false;
+        }
+        self.add_root(node);
+    }
+
+    // a node that loses a second child is cut as well, and so on up the tree. this is what
+    // stops decrease_key from leaving behind wide, shallow trees
+    fn cascading_cut(&mut self, mut node: *mut Node<T>) {
+        unsafe {
+            while !(*node).parent.is_null() {
+                if !(*node).marked {
+                    (*node).marked = true;
+                    return;
+                }
+                let parent = (*node).parent;
+                self.cut(node, parent);
+                node = parent;
+            }
+        }
+    }
+
+    // links roots of equal degree until every degree is unique, then finds the new top
+    fn consolidate(&mut self) {
+        let mut roots = Vec::new();
+        let start = self.top;
+        let mut current = start;
+        loop {
+            roots.push(current);
+            current = unsafe { (*current).right };
+            if current == start {
+                break;
+            }
+        }
+
+        // by_degree[d] is the root of degree d seen so far
+        let mut by_degree: Vec<*mut Node<T>> = Vec::new();
+        for root in roots {
+            let mut node = root;
+            let mut degree = unsafe { (*node).degree };
+            loop {
+                if by_degree.len() <= degree {
+                    by_degree.resize(degree + 1, ptr::null_mut());
+                }
+                let other = by_degree[degree];
+                if other.is_null() {
+                    break;
+                }
+                by_degree[degree] = ptr::null_mut();
+
+                let (parent, child) = if self.greater(self.value(other), self.value(node)) { (other, node) } else { (node, other) };
+                self.link(child, parent);
+                node = parent;
+                degree += 1;
+            }
+            by_degree[degree] = node;
+        }
+
+        self.top = ptr::null_mut();
+        for root in by_degree.into_iter().filter(|root| !root.is_null()) {
+            if self.top.is_null() || self.greater(self.value(root), self.value(self.top)) {
+                self.top = root;
+            }
+        }
+    }
+
+    // makes the root `child` a child of the root `parent`
+    fn link(&mut self, child: *mut Node<T>, parent: *mut Node<T>) {
+        unsafe {
+            unlink(child);
+            if (*parent).child.is_null() {
+                (*parent).child = child;
+            } else {
+                splice((*parent).child, child);
+            }
+            (*child).parent = parent;
+            (*child).marked = false;
+            (*parent).degree += 1;
+        }
+    }
+
+    fn greater(&self, a: &T, b: &T) -> bool {
+        self.cmp.compare(a, b) == Ordering::Greater
+    }
+}
+
+impl<T, C: Default> Default for FibonacciHeap<T, C> {
+    fn default() -> Self {
+        Self::with_comparator(C::default())
+    }
+}
+
+// joins two circular lists into one
+unsafe fn splice<T>(a: *mut Node<T>, b: *mut Node<T>) {
+    unsafe {
+        let a_right = (*a).right;
+        let b_left = (*b).left;
+        (*a).right = b;
+        (*b).left = a;
+        (*b_left).right = a_right;
+        (*a_right).left = b_left;
+    }
+}
+
+// takes a node out of its circular list, leaving it a list of its own
+unsafe fn unlink<T>(node: *mut Node<T>) {
+    unsafe {
+        (*(*node).left).right = (*node).right;
+        (*(*node).right).left = (*node).left;
+        (*node).left = node;
+        (*node).right = node;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the nodes of one circular list, starting anywhere
+    fn siblings<T>(first: *mut Node<T>) -> Vec<*mut Node<T>> {
+        let mut nodes = Vec::new();
+        if first.is_null() {
+            return nodes;
+        }
+
+        let mut current = first;
+        loop {
+            unsafe {
+                assert!((*(*current).right).left == current);
+                nodes.push(current);
+                current = (*current).right;
+            }
+            if current == first {
+                break;
+            }
+        }
+        nodes
+    }
+
+    // checks heap order, every link, and that each subtree of degree k holds at least
+    // F(k + 2) nodes, which is what makes the degrees no more than log_phi(n)
+    fn assert_invariants<T, C: Compare<T>>(heap: &FibonacciHeap<T, C>) {
+        fn check<T, C: Compare<T>>(heap: &FibonacciHeap<T, C>, node: *mut Node<T>, fibonacci: &[usize]) -> usize {
+            let children = siblings(unsafe { (*node).child });
+            let mut size = 1;
+            unsafe {
+                assert_eq!((*node).degree, children.len());
+                for &child in &children {
+                    assert!((*child).parent == node);
+                    assert!(!heap.greater(&(*child).value, &(*node).value));
+                    size += check(heap, child, fibonacci);
+                }
+                assert!(size >= fibonacci[(*node).degree + 2]);
+            }
+            size
+        }
+
+        let mut fibonacci = vec![0, 1];
+        while fibonacci.len() < 64 {
+            fibonacci.push(fibonacci[fibonacci.len() - 1] + fibonacci[fibonacci.len() - 2]);
+        }
+
+        let phi = (1.0 + 5f64.sqrt()) / 2.0;
+        let max_degree = if heap.size == 0 { 0 } else { (heap.size as f64).log(phi).floor() as usize };
+        let mut total = 0;
+        for root in siblings(heap.top) {
+            unsafe {
+                assert!((*root).parent.is_null());
+                assert!((*root).degree <= max_degree);
+                assert!(!heap.greater(&(*root).value, &(*heap.top).value));
+            }
+            total += check(heap, root, &fibonacci);
+        }
+        assert_eq!(total, heap.size);
+    }
+
+    #[test]
+    fn test_invariants_after_random_operations() {
+        let mut state = 0x0123_4567_89ab_cdefu64;
+        let mut next = |bound: u64| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state % bound
+        };
+
+        let mut heap = FibonacciHeap::min();
+        let mut live: Vec<(NodeHandle, i64)> = Vec::new();
+        for step in 0..6_000 {
+            match next(10) {
+                0..=3 => {
+                    let value = next(10_000) as i64;
+                    live.push((heap.push(value), value));
+                }
+                4 | 5 if !live.is_empty() => {
+                    let i = next(live.len() as u64) as usize;
+                    let value = live[i].1 - next(2_000) as i64;
+                    heap.decrease_key(&live[i].0, value);
+                    live[i].1 = value;
+                }
+                6 if !live.is_empty() => {
+                    let (handle, value) = live.swap_remove(next(live.len() as u64) as usize);
+                    assert_eq!(heap.delete(&handle), Some(value));
+                }
+                7 => {
+                    // a small heap with trees of its own, so the merge joins two consolidated
+                    // root lists
+                    let mut other = FibonacciHeap::min();
+                    let mut added = Vec::new();
+                    for _ in 0..next(20) {
+                        let value = next(10_000) as i64;
+                        added.push((other.push(value), value));
+                    }
+                    if let Some(value) = other.pop() {
+                        let i = added.iter().position(|(handle, _)| !other.contains(handle)).unwrap();
+                        assert_eq!(added.swap_remove(i).1, value);
+                    }
+                    heap.merge(other);
+                    live.extend(added);
+                }
+                _ => {
+                    let min = live.iter().map(|&(_, value)| value).min();
+                    assert_eq!(heap.pop(), min);
+                    if min.is_some() {
+                        let i = live.iter().position(|(handle, _)| !heap.contains(handle)).unwrap();
+                        live.swap_remove(i);
+                    }
+                }
+            }
+
+            assert_eq!(heap.size(), live.len());
+            if step % 10 == 0 {
+                assert_invariants(&heap);
+            }
+        }
+        assert_invariants(&heap);
+    }
+
+    #[test]
+    fn test_push_and_merge_are_lazy() {
+        let mut heap = FibonacciHeap::min();
+        for value in 0..10 {
+            heap.push(value);
+        }
+        let mut other = FibonacciHeap::min();
+        for value in 10..15 {
+            other.push(value);
+        }
+        heap.merge(other);
+
+        // nothing is linked until a pop
+        assert_eq!(siblings(heap.top).len(), 15);
+        assert_eq!(heap.pop(), Some(0));
+        assert_eq!(siblings(heap.top).len(), 14u32.count_ones() as usize);
+        assert_invariants(&heap);
+    }
+
+    #[test]
+    fn test_cascading_cut() {
+        // popping one of 16 items consolidates the other 15 into binomial trees of degree
+        // 0, 1, 2 and 3
+        let mut heap = FibonacciHeap::min();
+        let handles: Vec<NodeHandle> = (0..16).map(|value| heap.push(value)).collect();
+        heap.pop();
+        assert_eq!(siblings(heap.top).len(), 4);
+
+        // find a non-root node with two children
+        let node = handles
+            .iter()
+            .filter_map(|handle| heap.pool.resolve(handle))
+            .find(|&node| unsafe { !(*node).parent.is_null() && (*node).degree == 2 })
+            .unwrap();
+        let children = siblings(unsafe { (*node).child });
+        let handle_of = |target: *mut Node<i32>| heap.pool.handle(target);
+        let (first, second) = (handle_of(children[0]), handle_of(children[1]));
+
+        // losing one child only marks the node
+        heap.decrease_key(&first, -1);
+        unsafe { assert!((*node).marked && !(*node).parent.is_null()) };
+        assert_eq!(siblings(heap.top).len(), 5);
+
+        // losing a second cuts it loose as well
+        heap.decrease_key(&second, -2);
+        unsafe { assert!(!(*node).marked && (*node).parent.is_null()) };
+        assert_eq!(siblings(heap.top).len(), 7);
+        assert_eq!(heap.peek(), Some(&-2));
+        assert_invariants(&heap);
+    }
+
+    #[test]
+    fn test_delete() {
+        let mut heap = FibonacciHeap::min();
+        let handles: Vec<NodeHandle> = [5, 3, 8, 1, 9].into_iter().map(|value| heap.push(value)).collect();
+        heap.pop();
+
+        assert_eq!(heap.delete(&handles[2]), Some(8));
+        assert_eq!(heap.delete(&handles[2]), None);
+        assert_eq!(heap.delete(&handles[1]), Some(3));
+        assert_invariants(&heap);
+        assert_eq!(std::iter::from_fn(|| heap.pop()).collect::<Vec<_>>(), vec![5, 9]);
+    }
+
+    #[test]
+    fn test_comparators() {
+        let mut heap = FibonacciHeap::new();
+        let handles: Vec<NodeHandle> = (0..10).map(|value| heap.push(value)).collect();
+        assert_eq!(heap.pop(), Some(9));
+        // with the natural order decrease_key raises the value, cutting the node loose
+        heap.decrease_key(&handles[0], 20);
+        assert_invariants(&heap);
+        assert_eq!(heap.pop(), Some(20));
+        assert_eq!(heap.pop(), Some(8));
+
+        let mut words = FibonacciHeap::min_by_key(|word: &&str| word.len());
+        for word in ["abcd", "ab", "abc"] {
+            words.push(word);
+        }
+        assert_eq!(words.pop(), Some("ab"));
+        assert_invariants(&words);
+        assert_eq!(words.peek(), Some(&"abc"));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_decrease_key_rejects_increase() {
+        let mut heap = FibonacciHeap::min();
+        let handle = heap.push(5);
+        heap.decrease_key(&handle, 6);
+    }
+}
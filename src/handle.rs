@@ -0,0 +1,421 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::rc::Rc;
+
+// a stable reference to an item in one of the addressable heaps. it stays valid however the
+// heap rearranges itself, until the item is popped or removed; after that the handle is
+// stale and every lookup with it fails, even once the slot is reused for a new item
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Handle {
+    index: usize,
+    generation: u64,
+}
+
+impl Handle {
+    // the slot the item lives in, for structures that link their items by index
+    pub(crate) fn index(self) -> usize {
+        self.index
+    }
+}
+
+// the storage behind handles: a Vec of slots with a free list, where each slot counts how
+// many times it has been freed so that handles to earlier occupants can be told apart
+pub(crate) struct Slab<V> {
+    slots: Vec<Slot<V>>,
+    free: Vec<usize>,
+}
+
+struct Slot<V> {
+    generation: u64,
+    value: Option<V>,
+}
+
+impl<V> Slab<V> {
+    pub(crate) fn new() -> Self {
+        Slab {
+            slots: Vec::new(),
+            free: Vec::new(),
+        }
+    }
+
+    pub(crate) fn insert(&mut self, value: V) -> Handle {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.slots.push(Slot { generation: 0, value: None });
+                self.slots.len() - 1
+            }
+        };
+
+        let slot = &mut self.slots[index];
+        slot.value = Some(value);
+        Handle {
+            index,
+            generation: slot.generation,
+        }
+    }
+
+    pub(crate) fn contains(&self, handle: Handle) -> bool {
+        self.get(handle).is_some()
+    }
+
+    pub(crate) fn get(&self, handle: Handle) -> Option<&V> {
+        let slot = self.slots.get(handle.index)?;
+        if slot.generation != handle.generation {
+            return None;
+        }
+        slot.value.as_ref()
+    }
+
+    pub(crate) fn remove(&mut self, handle: Handle) -> Option<V> {
+        self.get(handle)?;
+        Some(self.remove_at(handle.index))
+    }
+
+    // the structures link their items by slot index; these skip the generation check, and
+    // panic on an empty slot, since an internal link to a freed item is a bug
+    pub(crate) fn handle(&self, index: usize) -> Handle {
+        Handle {
+            index,
+            generation: self.slots[index].generation,
+        }
+    }
+
+    pub(crate) fn at_mut(&mut self, index: usize) -> &mut V {
+        self.slots[index].value.as_mut().expect("link to a freed slot")
+    }
+
+    pub(crate) fn remove_at(&mut self, index: usize) -> V {
+        let slot = &mut self.slots[index];
+        let value = slot.value.take().expect("link to a freed slot");
+        slot.generation += 1;
+        self.free.push(index);
+        value
+    }
+
+    // frees every slot, invalidating every outstanding handle
+    pub(crate) fn clear(&mut self) {
+        for index in 0..self.slots.len() {
+            if self.slots[index].value.is_some() {
+                self.remove_at(index);
+            }
+        }
+    }
+}
+
+// the handle type of the pointer-based heaps (pairing, binomial, Fibonacci). those heaps
+// keep their nodes in a Pool, and the handle is the node's address plus the generation check
+// Slab does. a node's address never changes, so merging two heaps doesn't invalidate handles
+// into either of them
+#[derive(Clone)]
+pub struct NodeHandle {
+    ptr: *const (),
+    generation: u64,
+    owner: Owner,
+}
+
+impl PartialEq for NodeHandle {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr == other.ptr && self.generation == other.generation
+    }
+}
+
+impl Eq for NodeHandle {}
+
+impl fmt::Debug for NodeHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NodeHandle").field("ptr", &self.ptr).field("generation", &self.generation).finish()
+    }
+}
+
+// which pool a handle's node belongs to. merging makes the absorbed pool's owner point at the
+// survivor's, so this is a small union-find: two handles share a pool iff their owners share
+// a root
+#[derive(Clone)]
+struct Owner(Rc<RefCell<Option<Owner>>>);
+
+impl Owner {
+    fn new() -> Self {
+        Owner(Rc::new(RefCell::new(None)))
+    }
+
+    fn root(&self) -> Owner {
+        let mut root = self.clone();
+        loop {
+            let parent = root.0.borrow().clone();
+            match parent {
+                Some(parent) => root = parent,
+                None => break,
+            }
+        }
+
+        // path compression: point everything on the way straight at the root
+        let mut current = self.clone();
+        while !Rc::ptr_eq(&current.0, &root.0) {
+            let parent = current.0.replace(Some(root.clone()));
+            current = parent.unwrap();
+        }
+        root
+    }
+}
+
+// where the pointer-based heaps allocate their nodes. slots come in chunks that never move
+// and are only freed with the pool, so a pointer into the pool stays dereferenceable for the
+// pool's whole life, even after its slot is freed and reused. that's what lets a handle check
+// a slot's generation without risking a read of freed memory; checking the owner first
+// makes sure the slot is in this pool at all
+pub(crate) struct Pool<V> {
+    chunks: Vec<*mut [PoolSlot<V>]>,
+    free: *mut PoolSlot<V>, // a list threaded through the free slots
+    free_tail: *mut PoolSlot<V>,
+    capacity: usize,
+    owner: Owner,
+}
+
+// repr(C) with the value first, so a pointer to the value is also a pointer to its slot
+#[repr(C)]
+struct PoolSlot<V> {
+    value: MaybeUninit<V>,
+    generation: u64, // odd while the slot holds a value
+    next_free: *mut PoolSlot<V>,
+}
+
+const MIN_CHUNK: usize = 16;
+
+impl<V> Pool<V> {
+    pub(crate) fn new() -> Self {
+        Pool {
+            chunks: Vec::new(),
+            free: ptr::null_mut(),
+            free_tail: ptr::null_mut(),
+            capacity: 0,
+            owner: Owner::new(),
+        }
+    }
+
+    pub(crate) fn alloc(&mut self, value: V) -> *mut V {
+        if self.free.is_null() {
+            self.grow();
+        }
+
+        unsafe {
+            let slot = self.free;
+            self.free = (*slot).next_free;
+            if self.free.is_null() {
+                self.free_tail = ptr::null_mut();
+            }
+            (*slot).value.write(value);
+            (*slot).generation += 1;
+            slot as *mut V
+        }
+    }
+
+    // `node` must be an occupied slot of this pool
+    pub(crate) fn free(&mut self, node: *mut V) -> V {
+        let slot = node as *mut PoolSlot<V>;
+        unsafe {
+            debug_assert!((*slot).generation % 2 == 1, "freeing a free slot");
+            let value = (*slot).value.assume_init_read();
+            (*slot).generation += 1;
+            (*slot).next_free = self.free;
+            if self.free.is_null() {
+                self.free_tail = slot;
+            }
+            self.free = slot;
+            value
+        }
+    }
+
+    // `node` must be an occupied slot of this pool
+    pub(crate) fn handle(&self, node: *mut V) -> NodeHandle {
+        NodeHandle {
+            ptr: node as *const (),
+            generation: unsafe { (*(node as *mut PoolSlot<V>)).generation },
+            owner: self.owner.clone(),
+        }
+    }
+
+    // the handle's node, if it's in this pool and still holds the item the handle was made for
+    pub(crate) fn resolve(&self, handle: &NodeHandle) -> Option<*mut V> {
+        if !Rc::ptr_eq(&handle.owner.root().0, &self.owner.0) {
+            return None;
+        }
+
+        let slot = handle.ptr as *mut PoolSlot<V>;
+        if unsafe { (*slot).generation } != handle.generation {
+            return None;
+        }
+        Some(slot as *mut V)
+    }
+
+    // takes over every slot of `other`, occupied or free, in O(number of chunks), which is
+    // O(log n) since chunks double in size. handles into `other` now resolve in this pool
+    pub(crate) fn absorb(&mut self, other: &mut Pool<V>) {
+        self.chunks.append(&mut other.chunks);
+        self.capacity += other.capacity;
+        other.capacity = 0;
+
+        if !other.free.is_null() {
+            if self.free.is_null() {
+                self.free = other.free;
+            } else {
+                unsafe { (*self.free_tail).next_free = other.free };
+            }
+            self.free_tail = other.free_tail;
+            other.free = ptr::null_mut();
+            other.free_tail = ptr::null_mut();
+        }
+
+        let absorbed = other.owner.root();
+        if !Rc::ptr_eq(&absorbed.0, &self.owner.0) {
+            *absorbed.0.borrow_mut() = Some(self.owner.clone());
+        }
+    }
+
+    fn grow(&mut self) {
+        let size = self.capacity.max(MIN_CHUNK);
+        let slots: Box<[PoolSlot<V>]> = (0..size)
+            .map(|_| PoolSlot {
+                value: MaybeUninit::uninit(),
+                generation: 0,
+                next_free: ptr::null_mut(),
+            })
+            .collect();
+        let chunk = Box::into_raw(slots);
+        let first = chunk as *mut PoolSlot<V>;
+        for i in 0..size - 1 {
+            unsafe { (*first.add(i)).next_free = first.add(i + 1) };
+        }
+        self.chunks.push(chunk);
+        self.capacity += size;
+        // only called with the free list empty
+        self.free = first;
+        self.free_tail = unsafe { first.add(size - 1) };
+    }
+}
+
+impl<V> Drop for Pool<V> {
+    fn drop(&mut self) {
+        for &chunk in &self.chunks {
+            let mut chunk = unsafe { Box::from_raw(chunk) };
+            for slot in chunk.iter_mut() {
+                if slot.generation % 2 == 1 {
+                    unsafe { slot.value.assume_init_drop() };
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_get_remove() {
+        let mut slab = Slab::new();
+        let a = slab.insert("a");
+        let b = slab.insert("b");
+        assert_eq!(slab.get(a), Some(&"a"));
+        assert_eq!(slab.get(b), Some(&"b"));
+
+        *slab.at_mut(b.index()) = "B";
+        assert_eq!(slab.remove(b), Some("B"));
+        assert_eq!(slab.remove(b), None);
+        assert!(!slab.contains(b));
+        assert!(slab.contains(a));
+    }
+
+    #[test]
+    fn test_stale_handles_stay_stale() {
+        let mut slab = Slab::new();
+        let old = slab.insert(1);
+        slab.remove(old);
+
+        // the slot is reused, but under a new generation
+        let new = slab.insert(2);
+        assert_eq!(old.index(), new.index());
+        assert_ne!(old, new);
+        assert_eq!(slab.get(old), None);
+        assert_eq!(slab.get(new), Some(&2));
+
+        slab.clear();
+        assert!(!slab.contains(new));
+        let newest = slab.insert(3);
+        assert_eq!(slab.handle(newest.index()), newest);
+    }
+
+    #[test]
+    fn test_pool_handles() {
+        let mut pool = Pool::new();
+        let a = pool.alloc(String::from("a"));
+        let handle = pool.handle(a);
+        assert_eq!(pool.resolve(&handle), Some(a));
+        assert_eq!(unsafe { &*a }, "a");
+
+        assert_eq!(pool.free(a), "a");
+        assert_eq!(pool.resolve(&handle), None);
+
+        // the slot comes back under a new generation
+        let b = pool.alloc(String::from("b"));
+        assert_eq!(a, b);
+        assert_eq!(pool.resolve(&handle), None);
+        assert_eq!(pool.resolve(&pool.handle(b)), Some(b));
+
+        // a handle from another pool never resolves
+        let other = Pool::<String>::new();
+        assert_eq!(other.resolve(&pool.handle(b)), None);
+    }
+
+    #[test]
+    fn test_pool_absorb() {
+        let mut pools: Vec<Pool<usize>> = (0..4).map(|_| Pool::new()).collect();
+        let mut handles = Vec::new();
+        for (i, pool) in pools.iter_mut().enumerate() {
+            for j in 0..40 {
+                let node = pool.alloc(i * 100 + j);
+                handles.push((pool.handle(node), i * 100 + j));
+                if j % 3 == 0 {
+                    pool.free(node);
+                    handles.pop();
+                }
+            }
+        }
+
+        // absorb in a chain, so some owners end up two links from the root
+        let mut last = pools.pop().unwrap();
+        let mut third = pools.pop().unwrap();
+        third.absorb(&mut last);
+        let mut survivor = pools.pop().unwrap();
+        survivor.absorb(&mut third);
+        survivor.absorb(&mut pools[0]);
+
+        for (handle, value) in &handles {
+            let node = survivor.resolve(handle).unwrap();
+            assert_eq!(unsafe { *node }, *value);
+            assert_eq!(third.resolve(handle), None);
+        }
+
+        // the free slots of every pool are reusable
+        for i in 0..200 {
+            survivor.alloc(i);
+        }
+        assert_eq!(survivor.capacity, survivor.chunks.iter().map(|&chunk| chunk.len()).sum::<usize>());
+    }
+
+    #[test]
+    fn test_pool_drops_live_values() {
+        let shared = Rc::new(());
+        let mut pool = Pool::new();
+        let nodes: Vec<_> = (0..50).map(|_| pool.alloc(Rc::clone(&shared))).collect();
+        for &node in &nodes[..20] {
+            pool.free(node);
+        }
+        assert_eq!(Rc::strong_count(&shared), 31);
+
+        drop(pool);
+        assert_eq!(Rc::strong_count(&shared), 1);
+    }
+}
@@ -0,0 +1,1971 @@
+use std::borrow::Borrow;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash};
+use std::iter::Chain;
+use std::{mem, slice};
+
+// the table keeps at most 3/4 of its slots in use (live entries plus tombstones),
+// so every probe sequence is guaranteed to reach an empty slot
+const MAX_LOAD_NUMERATOR: usize = 3;
+const MAX_LOAD_DENOMINATOR: usize = 4;
+// every table has room for at least one full group of control bytes
+const MIN_SLOTS: usize = GROUP_WIDTH;
+
+// how colliding entries are placed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Strategy {
+    // an entry takes the first free slot after its home slot; removals leave tombstones
+    #[default]
+    Linear,
+    // an inserted entry evicts any entry that sits closer to its own home slot, keeping
+    // displacements even; removals shift the following entries back instead of leaving tombstones
+    RobinHood,
+    // swiss table layout: a separate array of one-byte control words (7 bits of each entry's
+    // hash, or EMPTY/DELETED) is scanned a group of 16 at a time, and only slots whose control
+    // byte matches are compared against the key. groups are probed quadratically
+    Swiss,
+}
+
+// how far entries sit from their home position (0 means at home). displacement is measured
+// in slots, except under `Strategy::Swiss` where it counts whole groups probed past the first
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProbeStats {
+    pub max: usize,
+    pub mean: f64,
+    // `histogram[d]` is the number of entries at displacement `d`
+    pub histogram: Vec<usize>,
+}
+
+enum Slot<K, V> {
+    Empty,
+    // a removed entry; lookups keep probing past it, inserts may reuse it.
+    // only the linear strategy ever leaves these behind (the swiss table marks them in `ctrl`)
+    Tombstone,
+    Occupied(Bucket<K, V>),
+}
+
+struct Bucket<K, V> {
+    hash: u64, // cached so resizing never has to rehash a key
+    displacement: usize, // distance from the home slot (or group)
+    key: K,
+    value: V,
+}
+
+// open addressing: an entry lives at or after its home slot `hash % slots.len()`,
+// wrapping around the end of the array
+struct Table<K, V> {
+    slots: Vec<Slot<K, V>>,
+    // one control byte per slot, only used by the swiss strategy
+    ctrl: Vec<u8>,
+    len: usize,
+    tombstones: usize,
+    strategy: Strategy,
+}
+
+impl<K, V> Table<K, V> {
+    fn with_slots(count: usize, strategy: Strategy) -> Self {
+        let ctrl = if strategy == Strategy::Swiss { vec![EMPTY; count] } else { Vec::new() };
+        Table {
+            slots: (0..count).map(|_| Slot::Empty).collect(),
+            ctrl,
+            len: 0,
+            tombstones: 0,
+            strategy,
+        }
+    }
+
+    // slot counts are always zero or a power of two, so the modulo is a mask
+    fn mask(&self) -> usize {
+        self.slots.len().wrapping_sub(1)
+    }
+
+    fn capacity(&self) -> usize {
+        usable_slots(self.slots.len())
+    }
+
+    fn find<Q>(&self, hash: u64, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        if self.slots.is_empty() {
+            return None;
+        }
+        if self.strategy == Strategy::Swiss {
+            return self.find_in_groups(hash, key);
+        }
+
+        let mask = self.mask();
+        let mut index = hash as usize & mask;
+        let mut displacement = 0;
+        loop {
+            match &self.slots[index] {
+                Slot::Empty => return None,
+                Slot::Occupied(bucket) => {
+                    if bucket.hash == hash && bucket.key.borrow() == key {
+                        return Some(index);
+                    }
+                    // robin hood keeps every run sorted by displacement, so meeting an entry
+                    // closer to home than we are means the key would have been placed here
+                    if self.strategy == Strategy::RobinHood && bucket.displacement < displacement {
+                        return None;
+                    }
+                }
+                Slot::Tombstone => {}
+            }
+            index = (index + 1) & mask;
+            displacement += 1;
+        }
+    }
+
+    // the caller guarantees the key is absent and that there is room for it;
+    // returns the slot the new entry ended up in
+    fn insert_new(&mut self, hash: u64, key: K, value: V) -> usize {
+        if self.strategy == Strategy::Swiss {
+            return self.insert_into_groups(hash, key, value);
+        }
+
+        let mask = self.mask();
+        let mut index = hash as usize & mask;
+        let mut bucket = Bucket { hash, displacement: 0, key, value };
+
+        if self.strategy == Strategy::Linear {
+            while let Slot::Occupied(_) = self.slots[index] {
+                index = (index + 1) & mask;
+                bucket.displacement += 1;
+            }
+
+            if let Slot::Tombstone = self.slots[index] {
+                self.tombstones -= 1;
+            }
+            self.slots[index] = Slot::Occupied(bucket);
+            self.len += 1;
+            return index;
+        }
+
+        // robin hood: whenever the entry in hand is further from home than the resident,
+        // they trade places and the evicted resident carries on probing
+        let mut placed = None;
+        loop {
+            match &mut self.slots[index] {
+                Slot::Occupied(resident) => {
+                    if resident.displacement < bucket.displacement {
+                        mem::swap(resident, &mut bucket);
+                        placed.get_or_insert(index);
+                    }
+                }
+                slot => {
+                    *slot = Slot::Occupied(bucket);
+                    self.len += 1;
+                    return placed.unwrap_or(index);
+                }
+            }
+            index = (index + 1) & mask;
+            bucket.displacement += 1;
+        }
+    }
+
+    fn remove_at(&mut self, index: usize) -> (K, V) {
+        let mask = self.mask();
+        let next = (index + 1) & mask;
+        let removed = match self.strategy {
+            // if the next slot is empty no probe sequence runs through this one,
+            // so it can go straight back to empty instead of leaving a tombstone
+            Strategy::Linear => {
+                let replacement = if let Slot::Empty = self.slots[next] {
+                    Slot::Empty
+                } else {
+                    self.tombstones += 1;
+                    Slot::Tombstone
+                };
+                mem::replace(&mut self.slots[index], replacement)
+            }
+            // backward shift: pull each following displaced entry one slot closer to home
+            // until reaching an empty slot or an entry already in its home slot
+            Strategy::RobinHood => {
+                let removed = mem::replace(&mut self.slots[index], Slot::Empty);
+                let mut hole = index;
+                loop {
+                    let next = (hole + 1) & mask;
+                    match &mut self.slots[next] {
+                        Slot::Occupied(bucket) if bucket.displacement > 0 => bucket.displacement -= 1,
+                        _ => break,
+                    }
+                    self.slots.swap(hole, next);
+                    hole = next;
+                }
+                removed
+            }
+            Strategy::Swiss => {
+                // aligned groups are only ever scanned whole, so if this group still has an
+                // EMPTY byte every lookup passing through stops here anyway and the slot can be
+                // freed outright; otherwise later groups may depend on it and it becomes DELETED
+                let group = Group::load(&self.ctrl, index - index % GROUP_WIDTH);
+                if group.match_empty().any() {
+                    self.ctrl[index] = EMPTY;
+                } else {
+                    self.ctrl[index] = DELETED;
+                    self.tombstones += 1;
+                }
+                mem::replace(&mut self.slots[index], Slot::Empty)
+            }
+        };
+
+        self.len -= 1;
+        match removed {
+            Slot::Occupied(bucket) => (bucket.key, bucket.value),
+            _ => unreachable!("remove_at called on a slot without an entry"),
+        }
+    }
+
+    fn bucket(&self, index: usize) -> &Bucket<K, V> {
+        match &self.slots[index] {
+            Slot::Occupied(bucket) => bucket,
+            _ => unreachable!("index does not point at an entry"),
+        }
+    }
+
+    fn bucket_mut(&mut self, index: usize) -> &mut Bucket<K, V> {
+        match &mut self.slots[index] {
+            Slot::Occupied(bucket) => bucket,
+            _ => unreachable!("index does not point at an entry"),
+        }
+    }
+
+    fn clear(&mut self) {
+        for slot in &mut self.slots {
+            *slot = Slot::Empty;
+        }
+        self.ctrl.fill(EMPTY);
+        self.len = 0;
+        self.tombstones = 0;
+    }
+
+    // moves every entry into a fresh table with `count` slots, dropping all tombstones
+    fn resize(&mut self, count: usize) {
+        let old = mem::replace(self, Table::with_slots(count, self.strategy));
+        for slot in old.slots {
+            if let Slot::Occupied(bucket) = slot {
+                self.insert_new(bucket.hash, bucket.key, bucket.value);
+            }
+        }
+    }
+
+    // the swiss strategy's probe sequence visits whole groups: the home group picked by the
+    // hash, then 1, 2, 3, ... groups further on (triangular numbers reach every group exactly
+    // once when the group count is a power of two)
+    fn find_in_groups<Q>(&self, hash: u64, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        let mut probe = ProbeSeq::new(hash, self.slots.len());
+        loop {
+            let group = Group::load(&self.ctrl, probe.start);
+            for bit in group.match_byte(h2(hash)) {
+                let index = probe.start + bit;
+                if let Slot::Occupied(bucket) = &self.slots[index]
+                    && bucket.hash == hash
+                    && bucket.key.borrow() == key
+                {
+                    return Some(index);
+                }
+            }
+            if group.match_empty().any() {
+                return None;
+            }
+            probe.next();
+        }
+    }
+
+    fn insert_into_groups(&mut self, hash: u64, key: K, value: V) -> usize {
+        let mut probe = ProbeSeq::new(hash, self.slots.len());
+        let index = loop {
+            if let Some(bit) = Group::load(&self.ctrl, probe.start).match_empty_or_deleted().next() {
+                break probe.start + bit;
+            }
+            probe.next();
+        };
+
+        if self.ctrl[index] == DELETED {
+            self.tombstones -= 1;
+        }
+        self.ctrl[index] = h2(hash);
+        self.slots[index] = Slot::Occupied(Bucket { hash, displacement: probe.distance, key, value });
+        self.len += 1;
+        index
+    }
+
+    fn count_displacements(&self, histogram: &mut Vec<usize>) {
+        for slot in &self.slots {
+            if let Slot::Occupied(bucket) = slot {
+                if histogram.len() <= bucket.displacement {
+                    histogram.resize(bucket.displacement + 1, 0);
+                }
+                histogram[bucket.displacement] += 1;
+            }
+        }
+    }
+}
+
+impl ProbeStats {
+    fn from_histogram(histogram: Vec<usize>) -> Self {
+        let entries: usize = histogram.iter().sum();
+        let total: usize = histogram.iter().enumerate().map(|(displacement, count)| displacement * count).sum();
+        ProbeStats {
+            max: histogram.len().saturating_sub(1),
+            mean: if entries == 0 { 0.0 } else { total as f64 / entries as f64 },
+            histogram,
+        }
+    }
+}
+
+// where an entry lives: in the current table, or in the one an incremental resize is draining
+#[derive(Clone, Copy)]
+struct Location {
+    old: bool,
+    index: usize,
+}
+
+// control bytes: EMPTY and DELETED have the top bit set, a full slot stores the top 7 bits
+// of its hash with the top bit clear
+const EMPTY: u8 = 0b1111_1111;
+const DELETED: u8 = 0b1000_0000;
+const GROUP_WIDTH: usize = 16;
+
+fn h2(hash: u64) -> u8 {
+    (hash >> 57) as u8
+}
+
+struct ProbeSeq {
+    start: usize,
+    distance: usize,
+    group_mask: usize,
+}
+
+impl ProbeSeq {
+    fn new(hash: u64, slots: usize) -> Self {
+        let group_mask = slots / GROUP_WIDTH - 1;
+        ProbeSeq {
+            start: (hash as usize & group_mask) * GROUP_WIDTH,
+            distance: 0,
+            group_mask,
+        }
+    }
+
+    fn next(&mut self) {
+        self.distance += 1;
+        let group = (self.start / GROUP_WIDTH + self.distance) & self.group_mask;
+        self.start = group * GROUP_WIDTH;
+    }
+}
+
+// the set of matching positions in a group, bit i standing for the group's i-th slot
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct BitMask(u16);
+
+impl BitMask {
+    fn any(self) -> bool {
+        self.0 != 0
+    }
+}
+
+impl Iterator for BitMask {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.0 == 0 {
+            return None;
+        }
+        let bit = self.0.trailing_zeros() as usize;
+        self.0 &= self.0 - 1;
+        Some(bit)
+    }
+}
+
+// sixteen control bytes compared all at once. on x86_64 this uses SSE2, which every x86_64
+// cpu has; everywhere else it falls back to the portable version below
+#[cfg(target_arch = "x86_64")]
+use sse2::Group;
+
+#[cfg(not(target_arch = "x86_64"))]
+use portable::Group;
+
+#[cfg(target_arch = "x86_64")]
+mod sse2 {
+    use super::{BitMask, EMPTY, GROUP_WIDTH};
+    use std::arch::x86_64::{__m128i, _mm_cmpeq_epi8, _mm_loadu_si128, _mm_movemask_epi8, _mm_set1_epi8};
+
+    #[derive(Clone, Copy)]
+    pub(super) struct Group(__m128i);
+
+    impl Group {
+        pub(super) fn load(ctrl: &[u8], start: usize) -> Self {
+            let bytes = &ctrl[start..start + GROUP_WIDTH];
+            // SAFETY: sse2 is part of the x86_64 baseline, and the slice above is exactly
+            // 16 bytes long; the load has no alignment requirement
+            unsafe { Group(_mm_loadu_si128(bytes.as_ptr().cast())) }
+        }
+
+        pub(super) fn match_byte(self, byte: u8) -> BitMask {
+            // SAFETY: sse2 is part of the x86_64 baseline
+            unsafe {
+                let equal = _mm_cmpeq_epi8(self.0, _mm_set1_epi8(byte as i8));
+                BitMask(_mm_movemask_epi8(equal) as u16)
+            }
+        }
+
+        pub(super) fn match_empty(self) -> BitMask {
+            self.match_byte(EMPTY)
+        }
+
+        // both sentinels have the top bit set and full slots don't, so this is the sign mask
+        pub(super) fn match_empty_or_deleted(self) -> BitMask {
+            // SAFETY: sse2 is part of the x86_64 baseline
+            unsafe { BitMask(_mm_movemask_epi8(self.0) as u16) }
+        }
+    }
+}
+
+// the same operations on a u128, using the classic word-at-a-time zero byte test
+#[cfg_attr(target_arch = "x86_64", allow(dead_code))]
+mod portable {
+    use super::{BitMask, EMPTY, GROUP_WIDTH};
+
+    const LOW_BITS: u128 = u128::from_ne_bytes([0x7f; GROUP_WIDTH]);
+    const HIGH_BITS: u128 = u128::from_ne_bytes([0x80; GROUP_WIDTH]);
+
+    #[derive(Clone, Copy)]
+    pub(super) struct Group(u128);
+
+    impl Group {
+        pub(super) fn load(ctrl: &[u8], start: usize) -> Self {
+            let bytes: [u8; GROUP_WIDTH] = ctrl[start..start + GROUP_WIDTH].try_into().unwrap();
+            Group(u128::from_le_bytes(bytes))
+        }
+
+        pub(super) fn match_byte(self, byte: u8) -> BitMask {
+            // a byte of `x` is zero exactly where the control byte equals `byte`; adding 0x7f
+            // to the low seven bits sets the high bit of every nonzero byte without carrying
+            // into its neighbour
+            let x = self.0 ^ u128::from_ne_bytes([byte; GROUP_WIDTH]);
+            let zero_bytes = !(((x & LOW_BITS) + LOW_BITS) | x | LOW_BITS);
+            compress(zero_bytes)
+        }
+
+        pub(super) fn match_empty(self) -> BitMask {
+            self.match_byte(EMPTY)
+        }
+
+        pub(super) fn match_empty_or_deleted(self) -> BitMask {
+            compress(self.0 & HIGH_BITS)
+        }
+    }
+
+    // gathers the high bit of each byte into one bit per byte
+    fn compress(high_bits: u128) -> BitMask {
+        let mut mask = 0;
+        for i in 0..GROUP_WIDTH {
+            mask |= (((high_bits >> (i * 8 + 7)) & 1) as u16) << i;
+        }
+        BitMask(mask)
+    }
+}
+
+// how many entries a table with `slots` slots can hold before it has to grow
+fn usable_slots(slots: usize) -> usize {
+    slots / MAX_LOAD_DENOMINATOR * MAX_LOAD_NUMERATOR
+}
+
+// the smallest slot count that holds `entries` entries without exceeding the load factor
+fn slots_for(entries: usize) -> usize {
+    if entries == 0 {
+        return 0;
+    }
+
+    let mut slots = MIN_SLOTS;
+    while usable_slots(slots) < entries {
+        slots *= 2;
+    }
+    slots
+}
+
+// `S` builds the hasher used for keys; see the `hasher` module for the in-crate choices
+pub struct HashMap<K, V, S = RandomState> {
+    table: Table<K, V>,
+    // while an incremental resize is in progress, the previous table; entries are moved out
+    // of it a few at a time, and every key lives in exactly one of the two tables
+    old: Option<Table<K, V>>,
+    // old slots before this index have already been migrated
+    cursor: usize,
+    // the most entries a single operation may migrate, or None to resize all at once
+    incremental: Option<usize>,
+    hash_builder: S,
+    // every entry moved between tables, so tests can check how much work each operation did
+    #[cfg(test)]
+    moved: usize,
+}
+
+impl<K, V> HashMap<K, V, RandomState> {
+    pub fn new() -> Self {
+        Self::with_hasher(RandomState::new())
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, RandomState::new())
+    }
+
+    pub fn with_strategy(strategy: Strategy) -> Self {
+        Self::with_strategy_and_hasher(strategy, RandomState::new())
+    }
+}
+
+impl<K, V, S> HashMap<K, V, S> {
+    pub fn with_hasher(hash_builder: S) -> Self {
+        Self::with_strategy_and_hasher(Strategy::default(), hash_builder)
+    }
+
+    pub fn with_capacity_and_hasher(capacity: usize, hash_builder: S) -> Self {
+        let mut map = Self::with_hasher(hash_builder);
+        map.reserve(capacity);
+        map
+    }
+
+    pub fn with_strategy_and_hasher(strategy: Strategy, hash_builder: S) -> Self {
+        HashMap {
+            table: Table::with_slots(0, strategy),
+            old: None,
+            cursor: 0,
+            incremental: None,
+            hash_builder,
+            #[cfg(test)]
+            moved: 0,
+        }
+    }
+
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
+
+    pub fn strategy(&self) -> Strategy {
+        self.table.strategy
+    }
+
+    pub fn len(&self) -> usize {
+        self.table.len + self.old.as_ref().map_or(0, |old| old.len)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // the number of entries the map can hold without reallocating
+    pub fn capacity(&self) -> usize {
+        self.table.capacity()
+    }
+
+    // with Some(n), growing the table no longer rehashes everything in one go: the new table
+    // is allocated alongside the old one and each insert, remove or entry call moves at most
+    // `n` entries across until the old table is empty. lookups check both tables meanwhile.
+    // None switches back to resizing all at once, finishing any migration in progress
+    pub fn set_incremental_resize(&mut self, entries_per_operation: Option<usize>) {
+        assert!(entries_per_operation != Some(0), "incremental resizing must move at least one entry at a time");
+        self.incremental = entries_per_operation;
+        if entries_per_operation.is_none() {
+            self.finish_migration();
+        }
+    }
+
+    pub fn incremental_resize(&self) -> Option<usize> {
+        self.incremental
+    }
+
+    // whether an incremental resize is still moving entries out of the old table
+    pub fn is_resizing(&self) -> bool {
+        self.old.is_some()
+    }
+
+    // removes every entry but keeps the allocated slots
+    pub fn clear(&mut self) {
+        self.old = None;
+        self.table.clear();
+    }
+
+    // makes sure at least `additional` more entries fit without reallocating.
+    // like shrink_to_fit this is an explicit bulk operation, so it completes any
+    // incremental resize first
+    pub fn reserve(&mut self, additional: usize) {
+        self.finish_migration();
+        let required = self.len().checked_add(additional).expect("capacity overflow");
+        if required > self.capacity() {
+            self.resize(slots_for(required));
+        }
+    }
+
+    // shrinks the table as far as the load factor allows, also clearing out tombstones
+    pub fn shrink_to_fit(&mut self) {
+        self.finish_migration();
+        let slots = slots_for(self.len());
+        if slots < self.table.slots.len() || self.table.tombstones > 0 {
+            self.resize(slots);
+        }
+    }
+
+    // a snapshot of how far entries sit from their home slots, for checking table health
+    pub fn probe_stats(&self) -> ProbeStats {
+        let mut histogram = Vec::new();
+        self.table.count_displacements(&mut histogram);
+        if let Some(old) = &self.old {
+            old.count_displacements(&mut histogram);
+        }
+        ProbeStats::from_histogram(histogram)
+    }
+
+    // entries come out in slot order, which has nothing to do with insertion order
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        let old: &[Slot<K, V>] = self.old.as_ref().map_or(&[], |old| &old.slots);
+        Iter {
+            slots: self.table.slots.iter().chain(old),
+            remaining: self.len(),
+        }
+    }
+
+    pub fn keys(&self) -> Keys<'_, K, V> {
+        Keys { inner: self.iter() }
+    }
+
+    pub fn values(&self) -> Values<'_, K, V> {
+        Values { inner: self.iter() }
+    }
+
+    // guarantees a free slot in the current table for one more entry, counting tombstones
+    // as used since they lengthen probe sequences just like live entries do
+    fn make_room(&mut self) {
+        let table = &self.table;
+        if table.len + table.tombstones < table.capacity() {
+            return;
+        }
+
+        // mostly tombstones: rebuilding at the same size is enough to reclaim them
+        let slots = if table.len < table.capacity() / 2 {
+            table.slots.len()
+        } else {
+            slots_for(table.len + 1).max(table.slots.len() * 2)
+        };
+
+        // with nothing to carry over there's no point splitting the work up
+        if self.incremental.is_none() || table.len == 0 {
+            self.resize(slots);
+            return;
+        }
+
+        // the new table is at least twice the old table's live entries, and every operation
+        // that adds to it migrates at least one old entry first, so the old table should
+        // always drain before the new one fills. if it somehow hasn't, fold both tables into
+        // one big enough for everything rather than drop the old table's entries
+        if let Some(old) = &self.old {
+            let slots = slots_for(self.table.len + old.len + 1).max(slots);
+            self.resize(slots);
+            self.finish_migration();
+            return;
+        }
+
+        let new = Table::with_slots(slots, self.table.strategy);
+        self.old = Some(mem::replace(&mut self.table, new));
+        self.cursor = 0;
+    }
+
+    fn resize(&mut self, slots: usize) {
+        #[cfg(test)]
+        {
+            self.moved += self.table.len;
+        }
+        self.table.resize(slots);
+    }
+
+    // moves up to the configured number of entries from the old table into the current one
+    fn migrate(&mut self) {
+        if let Some(limit) = self.incremental {
+            self.migrate_up_to(limit);
+        }
+    }
+
+    fn finish_migration(&mut self) {
+        self.migrate_up_to(usize::MAX);
+    }
+
+    fn migrate_up_to(&mut self, limit: usize) {
+        let Some(old) = &mut self.old else {
+            return;
+        };
+
+        let mut moved = 0;
+        while moved < limit && old.len > 0 {
+            let Slot::Occupied(bucket) = &old.slots[self.cursor] else {
+                self.cursor += 1;
+                continue;
+            };
+
+            // removing normally keeps the old table's probe sequences intact for lookups.
+            // robin hood's backward shift may pull the next entry into this same slot,
+            // which is why the cursor only advances past free slots
+            let hash = bucket.hash;
+            let (key, value) = old.remove_at(self.cursor);
+            self.table.insert_new(hash, key, value);
+            moved += 1;
+        }
+
+        if old.len == 0 {
+            self.old = None;
+        }
+        #[cfg(test)]
+        {
+            self.moved += moved;
+        }
+    }
+
+    fn table(&self, location: Location) -> &Table<K, V> {
+        match &self.old {
+            Some(old) if location.old => old,
+            _ => &self.table,
+        }
+    }
+
+    fn table_mut(&mut self, location: Location) -> &mut Table<K, V> {
+        match &mut self.old {
+            Some(old) if location.old => old,
+            _ => &mut self.table,
+        }
+    }
+
+    fn remove_at(&mut self, location: Location) -> (K, V) {
+        let removed = self.table_mut(location).remove_at(location.index);
+        if self.old.as_ref().is_some_and(|old| old.len == 0) {
+            self.old = None;
+        }
+        removed
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher> HashMap<K, V, S> {
+    // returns the previous value if the key was already present
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.entry(key) {
+            Entry::Occupied(mut entry) => Some(entry.insert(value)),
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+                None
+            }
+        }
+    }
+
+    // finds the key's slot once; inserting through a vacant entry reuses the computed hash
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, S> {
+        self.migrate();
+        let hash = self.make_hash(&key);
+        match self.find(hash, &key) {
+            Some(location) => Entry::Occupied(OccupiedEntry { map: self, location }),
+            None => Entry::Vacant(VacantEntry { map: self, key, hash }),
+        }
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_key_value(key).map(|(_, value)| value)
+    }
+
+    // also hands back the stored key, which may be a distinct (but equal) value from the lookup key
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let location = self.find(self.make_hash(key), key)?;
+        let bucket = self.table(location).bucket(location.index);
+        Some((&bucket.key, &bucket.value))
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let location = self.find(self.make_hash(key), key)?;
+        Some(&mut self.table_mut(location).bucket_mut(location.index).value)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.find(self.make_hash(key), key).is_some()
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.remove_entry(key).map(|(_, value)| value)
+    }
+
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.migrate();
+        let location = self.find(self.make_hash(key), key)?;
+        Some(self.remove_at(location))
+    }
+
+    // swaps in `key` for an equal key already stored, keeping that key's value, and returns the
+    // old key. on a miss it inserts `key` with V::default() and returns None, which is what
+    // HashSet::replace wants (its values are all ()) but would surprise anyone else. either
+    // way the table is only probed once
+    pub(crate) fn replace_key_or_insert_default(&mut self, key: K) -> Option<K>
+    where
+        V: Default,
+    {
+        self.migrate();
+        let hash = self.make_hash(&key);
+        match self.find(hash, &key) {
+            Some(location) => {
+                let bucket = self.table_mut(location).bucket_mut(location.index);
+                Some(mem::replace(&mut bucket.key, key))
+            }
+            None => {
+                VacantEntry { map: self, key, hash }.insert(V::default());
+                None
+            }
+        }
+    }
+
+    fn make_hash<Q: Hash + ?Sized>(&self, key: &Q) -> u64 {
+        self.hash_builder.hash_one(key)
+    }
+
+    // new entries only ever go into the current table, so that's where most keys are
+    fn find<Q>(&self, hash: u64, key: &Q) -> Option<Location>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        if let Some(index) = self.table.find(hash, key) {
+            return Some(Location { old: false, index });
+        }
+        let index = self.old.as_ref()?.find(hash, key)?;
+        Some(Location { old: true, index })
+    }
+}
+
+impl<K, V, S: Default> Default for HashMap<K, V, S> {
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+type Slots<'a, K, V> = slice::Iter<'a, Slot<K, V>>;
+
+// walks the current table, then the old one if a resize is in progress
+pub struct Iter<'a, K, V> {
+    slots: Chain<Slots<'a, K, V>, Slots<'a, K, V>>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for slot in self.slots.by_ref() {
+            if let Slot::Occupied(bucket) = slot {
+                self.remaining -= 1;
+                return Some((&bucket.key, &bucket.value));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+impl<K, V> Clone for Iter<'_, K, V> {
+    fn clone(&self) -> Self {
+        Iter {
+            slots: self.slots.clone(),
+            remaining: self.remaining,
+        }
+    }
+}
+
+pub struct Keys<'a, K, V> {
+    inner: Iter<'a, K, V>,
+}
+
+impl<'a, K, V> Iterator for Keys<'a, K, V> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<&'a K> {
+        self.inner.next().map(|(key, _)| key)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K, V> ExactSizeIterator for Keys<'_, K, V> {}
+
+pub struct Values<'a, K, V> {
+    inner: Iter<'a, K, V>,
+}
+
+impl<'a, K, V> Iterator for Values<'a, K, V> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<&'a V> {
+        self.inner.next().map(|(_, value)| value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K, V> ExactSizeIterator for Values<'_, K, V> {}
+
+impl<'a, K, V, S> IntoIterator for &'a HashMap<K, V, S> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub enum Entry<'a, K, V, S = RandomState> {
+    Occupied(OccupiedEntry<'a, K, V, S>),
+    Vacant(VacantEntry<'a, K, V, S>),
+}
+
+pub struct OccupiedEntry<'a, K, V, S = RandomState> {
+    map: &'a mut HashMap<K, V, S>,
+    location: Location,
+}
+
+pub struct VacantEntry<'a, K, V, S = RandomState> {
+    map: &'a mut HashMap<K, V, S>,
+    key: K,
+    hash: u64,
+}
+
+impl<'a, K, V, S> Entry<'a, K, V, S> {
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    pub fn or_insert_with<F>(self, default: F) -> &'a mut V
+    where
+        F: FnOnce() -> V,
+    {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    pub fn or_insert_with_key<F>(self, default: F) -> &'a mut V
+    where
+        F: FnOnce(&K) -> V,
+    {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let value = default(&entry.key);
+                entry.insert(value)
+            }
+        }
+    }
+
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    pub fn and_modify<F>(mut self, modify: F) -> Self
+    where
+        F: FnOnce(&mut V),
+    {
+        if let Entry::Occupied(entry) = &mut self {
+            modify(entry.get_mut());
+        }
+        self
+    }
+}
+
+impl<'a, K, V, S> OccupiedEntry<'a, K, V, S> {
+    pub fn key(&self) -> &K {
+        &self.map.table(self.location).bucket(self.location.index).key
+    }
+
+    pub fn get(&self) -> &V {
+        &self.map.table(self.location).bucket(self.location.index).value
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut self.map.table_mut(self.location).bucket_mut(self.location.index).value
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        &mut self.map.table_mut(self.location).bucket_mut(self.location.index).value
+    }
+
+    pub fn insert(&mut self, value: V) -> V {
+        mem::replace(self.get_mut(), value)
+    }
+
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+
+    pub fn remove_entry(self) -> (K, V) {
+        self.map.remove_at(self.location)
+    }
+}
+
+impl<'a, K, V, S> VacantEntry<'a, K, V, S> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    pub fn insert(self, value: V) -> &'a mut V {
+        // growing moves every entry, so only look for a slot once there's room
+        self.map.make_room();
+        let index = self.map.table.insert_new(self.hash, self.key, value);
+        &mut self.map.table.bucket_mut(index).value
+    }
+
+    // stores `key` instead of the key the entry was looked up with. the two must hash and
+    // compare the same; LinkedHashMap probes with a pointer to the caller's key and then
+    // stores one to the copy it moved into its node
+    pub(crate) fn insert_with_key(self, key: K, value: V) -> &'a mut V {
+        VacantEntry { map: self.map, key, hash: self.hash }.insert(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hasher::{FxBuildHasher, SipBuildHasher};
+    use std::hash::{BuildHasherDefault, Hasher};
+
+    #[test]
+    fn test_new_map_is_empty() {
+        let map: HashMap<i32, i32> = HashMap::new();
+        assert!(map.is_empty());
+        assert_eq!(map.len(), 0);
+        assert_eq!(map.capacity(), 0);
+        assert_eq!(map.get(&1), None);
+    }
+
+    #[test]
+    fn test_insert_and_get() {
+        let mut map = HashMap::new();
+        assert_eq!(map.insert(1, "one"), None);
+        assert_eq!(map.insert(2, "two"), None);
+
+        assert_eq!(map.len(), 2);
+        assert!(!map.is_empty());
+        assert_eq!(map.get(&1), Some(&"one"));
+        assert_eq!(map.get(&2), Some(&"two"));
+        assert_eq!(map.get(&3), None);
+    }
+
+    #[test]
+    fn test_insert_replaces_existing_value() {
+        let mut map = HashMap::new();
+        assert_eq!(map.insert("key", 1), None);
+        assert_eq!(map.insert("key", 2), Some(1));
+
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.get("key"), Some(&2));
+    }
+
+    #[test]
+    fn test_get_mut() {
+        let mut map = HashMap::new();
+        map.insert(1, 10);
+
+        if let Some(value) = map.get_mut(&1) {
+            *value += 5;
+        }
+        assert_eq!(map.get(&1), Some(&15));
+        assert_eq!(map.get_mut(&2), None);
+    }
+
+    #[test]
+    fn test_contains_key() {
+        let mut map = HashMap::new();
+        assert!(!map.contains_key(&1));
+
+        map.insert(1, ());
+        assert!(map.contains_key(&1));
+        assert!(!map.contains_key(&2));
+
+        map.remove(&1);
+        assert!(!map.contains_key(&1));
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut map = HashMap::new();
+        map.insert(1, "one");
+        map.insert(2, "two");
+
+        assert_eq!(map.remove(&1), Some("one"));
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.remove(&1), None);
+        assert_eq!(map.get(&2), Some(&"two"));
+
+        assert_eq!(map.remove(&2), Some("two"));
+        assert!(map.is_empty());
+        assert_eq!(map.remove(&3), None);
+    }
+
+    #[test]
+    fn test_lookups_probe_past_tombstones() {
+        // with the table well over half full, removals leave tombstones in the
+        // middle of probe clusters that later lookups have to walk past
+        let mut map = HashMap::with_capacity(64);
+        for i in 0..40 {
+            map.insert(i, i * 10);
+        }
+        for i in (0..40).step_by(2) {
+            assert_eq!(map.remove(&i), Some(i * 10));
+        }
+
+        for i in 0..40 {
+            let expected = if i % 2 == 0 { None } else { Some(&(i * 10)) };
+            assert_eq!(map.get(&i), expected);
+        }
+        assert_eq!(map.len(), 20);
+    }
+
+    #[test]
+    fn test_tombstones_are_reused() {
+        for strategy in [Strategy::Linear, Strategy::Swiss] {
+            let mut map = HashMap::with_strategy(strategy);
+            map.reserve(16);
+            let capacity = map.capacity();
+
+            // churning through many more keys than fit must not grow the table,
+            // since the live count never exceeds a couple of entries
+            for i in 0..10_000 {
+                map.insert(i, i);
+                if i > 0 {
+                    assert_eq!(map.remove(&(i - 1)), Some(i - 1));
+                }
+            }
+            assert_eq!(map.len(), 1);
+            assert_eq!(map.capacity(), capacity);
+            assert_eq!(map.get(&9_999), Some(&9_999));
+        }
+    }
+
+    #[test]
+    fn test_grows_as_needed() {
+        let mut map = HashMap::new();
+        for i in 0..10_000 {
+            map.insert(i, i * 2);
+            assert!(map.len() <= map.capacity());
+        }
+
+        assert_eq!(map.len(), 10_000);
+        for i in 0..10_000 {
+            assert_eq!(map.get(&i), Some(&(i * 2)));
+        }
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut map = HashMap::new();
+        for i in 0..100 {
+            map.insert(i, i);
+        }
+        let capacity = map.capacity();
+
+        map.clear();
+        assert!(map.is_empty());
+        assert_eq!(map.get(&5), None);
+        assert_eq!(map.capacity(), capacity);
+
+        map.insert(5, 50);
+        assert_eq!(map.get(&5), Some(&50));
+    }
+
+    #[test]
+    fn test_reserve() {
+        let mut map = HashMap::new();
+        map.reserve(100);
+        let capacity = map.capacity();
+        assert!(capacity >= 100);
+
+        for i in 0..100 {
+            map.insert(i, i);
+        }
+        assert_eq!(map.capacity(), capacity);
+
+        map.reserve(0);
+        assert_eq!(map.capacity(), capacity);
+        map.reserve(capacity);
+        assert!(map.capacity() >= 100 + capacity);
+    }
+
+    #[test]
+    fn test_shrink_to_fit() {
+        let mut map = HashMap::new();
+        for i in 0..1_000 {
+            map.insert(i, i);
+        }
+        for i in 10..1_000 {
+            map.remove(&i);
+        }
+
+        map.shrink_to_fit();
+        assert!(map.capacity() >= 10);
+        assert!(map.capacity() < 100);
+        for i in 0..10 {
+            assert_eq!(map.get(&i), Some(&i));
+        }
+
+        map.clear();
+        map.shrink_to_fit();
+        assert_eq!(map.capacity(), 0);
+        map.insert(1, 1);
+        assert_eq!(map.get(&1), Some(&1));
+    }
+
+    #[test]
+    fn test_borrowed_lookups() {
+        let mut map = HashMap::new();
+        map.insert(String::from("apple"), 3);
+        map.insert(String::from("pear"), 5);
+
+        assert_eq!(map.get("apple"), Some(&3));
+        assert!(map.contains_key("pear"));
+        assert_eq!(map.remove("pear"), Some(5));
+        assert!(!map.contains_key("pear"));
+    }
+
+    #[test]
+    fn test_with_complex_types() {
+        let mut map: HashMap<String, Vec<i32>> = HashMap::new();
+        map.insert(String::from("evens"), vec![0, 2, 4]);
+        map.insert(String::from("odds"), vec![1, 3]);
+
+        map.get_mut("odds").unwrap().push(5);
+        assert_eq!(map.get("odds"), Some(&vec![1, 3, 5]));
+        assert_eq!(map.remove("evens"), Some(vec![0, 2, 4]));
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn test_drops_values() {
+        use std::rc::Rc;
+
+        let shared = Rc::new(());
+        let mut map = HashMap::new();
+        for i in 0..50 {
+            map.insert(i, Rc::clone(&shared));
+        }
+        map.remove(&0);
+        map.insert(1, Rc::clone(&shared));
+        assert_eq!(Rc::strong_count(&shared), 50);
+
+        drop(map);
+        assert_eq!(Rc::strong_count(&shared), 1);
+    }
+
+    // deterministic pseudo-random mix of inserts and removes over a small key space,
+    // so the table sees plenty of collisions, removals and resizes
+    fn check_against_std(strategy: Strategy) -> HashMap<u64, u64> {
+        let mut map = HashMap::with_strategy(strategy);
+        let mut reference = std::collections::HashMap::new();
+
+        let mut state = 0x2545_f491_u64;
+        for _ in 0..20_000 {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let key = state % 500;
+
+            if state.is_multiple_of(3) {
+                assert_eq!(map.remove(&key), reference.remove(&key));
+            } else {
+                assert_eq!(map.insert(key, state), reference.insert(key, state));
+            }
+            assert_eq!(map.len(), reference.len());
+        }
+
+        for key in 0..500 {
+            assert_eq!(map.get(&key), reference.get(&key));
+        }
+        map
+    }
+
+    // every entry's stored displacement is its real distance from home, and no entry sits
+    // further from home than the one before it can account for
+    fn assert_robin_hood_invariant<K, V>(map: &HashMap<K, V>) {
+        let table = &map.table;
+        let mask = table.mask();
+        for (index, slot) in table.slots.iter().enumerate() {
+            let bucket = match slot {
+                Slot::Occupied(bucket) => bucket,
+                Slot::Empty => continue,
+                Slot::Tombstone => panic!("robin hood table contains a tombstone"),
+            };
+            assert_eq!(bucket.displacement, index.wrapping_sub(bucket.hash as usize) & mask);
+
+            if bucket.displacement > 0 {
+                match &table.slots[index.wrapping_sub(1) & mask] {
+                    Slot::Occupied(previous) => assert!(previous.displacement + 1 >= bucket.displacement),
+                    _ => panic!("displaced entry at {} follows a free slot", index),
+                }
+            }
+        }
+    }
+
+    fn variance(stats: &ProbeStats) -> f64 {
+        let count: usize = stats.histogram.iter().sum();
+        let squares: f64 = stats.histogram.iter().enumerate()
+            .map(|(displacement, &entries)| (displacement as f64 - stats.mean).powi(2) * entries as f64)
+            .sum();
+        squares / count as f64
+    }
+
+    #[test]
+    fn test_matches_std_hash_map() {
+        let map = check_against_std(Strategy::Linear);
+        assert_eq!(map.strategy(), Strategy::Linear);
+    }
+
+    #[test]
+    fn test_robin_hood_matches_std_hash_map() {
+        let map = check_against_std(Strategy::RobinHood);
+        assert_eq!(map.strategy(), Strategy::RobinHood);
+        assert_robin_hood_invariant(&map);
+    }
+
+    #[test]
+    fn test_basic_operations_for_every_strategy() {
+        for strategy in [Strategy::Linear, Strategy::RobinHood, Strategy::Swiss] {
+            let mut map = HashMap::with_strategy(strategy);
+            assert_eq!(map.insert("a", 1), None);
+            assert_eq!(map.insert("b", 2), None);
+            assert_eq!(map.insert("a", 3), Some(1));
+
+            assert_eq!(map.get("a"), Some(&3));
+            *map.get_mut("b").unwrap() += 10;
+            assert_eq!(map.remove("b"), Some(12));
+            assert_eq!(map.get("b"), None);
+            assert_eq!(map.len(), 1);
+
+            map.clear();
+            assert!(map.is_empty());
+            assert_eq!(map.get("a"), None);
+            map.shrink_to_fit();
+            assert_eq!(map.capacity(), 0);
+        }
+    }
+
+    #[test]
+    fn test_robin_hood_removal_shifts_back() {
+        let mut map = HashMap::with_strategy(Strategy::RobinHood);
+        map.reserve(1_000);
+        for i in 0..1_000 {
+            map.insert(i, i);
+        }
+        for i in (0..1_000).filter(|i| i % 3 != 0) {
+            assert_eq!(map.remove(&i), Some(i));
+            if i % 100 == 1 {
+                assert_robin_hood_invariant(&map);
+            }
+        }
+
+        assert_eq!(map.table.tombstones, 0);
+        assert_robin_hood_invariant(&map);
+        for i in 0..1_000 {
+            assert_eq!(map.contains_key(&i), i % 3 == 0);
+        }
+    }
+
+    #[test]
+    fn test_probe_stats() {
+        let map: HashMap<i32, i32> = HashMap::new();
+        assert_eq!(map.probe_stats(), ProbeStats::default());
+
+        for strategy in [Strategy::Linear, Strategy::RobinHood, Strategy::Swiss] {
+            let mut map = HashMap::with_strategy(strategy);
+            for i in 0..5_000 {
+                map.insert(i, ());
+            }
+
+            let stats = map.probe_stats();
+            assert_eq!(stats.histogram.iter().sum::<usize>(), 5_000);
+            assert_eq!(stats.histogram.len(), stats.max + 1);
+            assert!(stats.histogram[stats.max] > 0);
+            assert!(stats.mean >= 0.0 && stats.mean <= stats.max as f64);
+        }
+    }
+
+    #[test]
+    fn test_robin_hood_evens_out_probe_lengths() {
+        // a fixed seed gives both maps the same hashes, so the comparison is deterministic
+        let hasher = SipBuildHasher::new(7);
+        let mut linear = HashMap::with_strategy_and_hasher(Strategy::Linear, hasher);
+        let mut robin_hood = HashMap::with_strategy_and_hasher(Strategy::RobinHood, hasher);
+        // fill both right up to the load limit, where linear probing clusters worst
+        let count = HashMap::<u32, ()>::with_capacity(10_000).capacity() as u32;
+        linear.reserve(count as usize);
+        robin_hood.reserve(count as usize);
+        for i in 0..count {
+            linear.insert(i, ());
+            robin_hood.insert(i, ());
+        }
+
+        let linear_stats = linear.probe_stats();
+        let robin_hood_stats = robin_hood.probe_stats();
+        assert!(robin_hood_stats.max <= linear_stats.max);
+        assert!(variance(&robin_hood_stats) < variance(&linear_stats));
+    }
+
+    // every control byte agrees with its slot, and the DELETED count is the tombstone count
+    fn assert_swiss_invariant<K, V>(map: &HashMap<K, V>) {
+        let table = &map.table;
+        assert_eq!(table.ctrl.len(), table.slots.len());
+        for (slot, &ctrl) in table.slots.iter().zip(&table.ctrl) {
+            match slot {
+                Slot::Occupied(bucket) => assert_eq!(ctrl, h2(bucket.hash)),
+                Slot::Empty => assert!(ctrl == EMPTY || ctrl == DELETED),
+                Slot::Tombstone => panic!("swiss table stores a tombstone slot"),
+            }
+        }
+        assert_eq!(table.ctrl.iter().filter(|&&ctrl| ctrl == DELETED).count(), table.tombstones);
+    }
+
+    #[test]
+    fn test_swiss_matches_std_hash_map() {
+        let map = check_against_std(Strategy::Swiss);
+        assert_eq!(map.strategy(), Strategy::Swiss);
+        assert_swiss_invariant(&map);
+    }
+
+    #[test]
+    fn test_swiss_removal_marks_control_bytes() {
+        let mut map = HashMap::with_strategy(Strategy::Swiss);
+        map.reserve(5_000);
+        for i in 0..5_000 {
+            map.insert(i, i);
+        }
+        for i in (0..5_000).step_by(2) {
+            assert_eq!(map.remove(&i), Some(i));
+        }
+        assert_swiss_invariant(&map);
+
+        for i in 0..5_000 {
+            assert_eq!(map.get(&i), if i % 2 == 0 { None } else { Some(&i) });
+        }
+        for i in (0..5_000).step_by(2) {
+            map.insert(i, i);
+        }
+        assert_swiss_invariant(&map);
+        assert_eq!(map.len(), 5_000);
+    }
+
+    #[test]
+    fn test_group_matching() {
+        let mut ctrl = vec![EMPTY; 32];
+        ctrl[16] = 0x15;
+        ctrl[18] = DELETED;
+        ctrl[20] = 0x15;
+        ctrl[31] = 0x7f;
+
+        let group = Group::load(&ctrl, 16);
+        assert_eq!(group.match_byte(0x15).collect::<Vec<_>>(), vec![0, 4]);
+        assert_eq!(group.match_byte(0x7f).collect::<Vec<_>>(), vec![15]);
+        assert!(!group.match_byte(0x00).any());
+        assert_eq!(group.match_empty().count(), 12);
+        assert_eq!(group.match_empty_or_deleted().count(), 13);
+        assert_eq!(Group::load(&ctrl, 0).match_empty().count(), 16);
+    }
+
+    #[test]
+    fn test_portable_group_agrees() {
+        let mut state = 0x9e37_79b9_u64;
+        let mut next_byte = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            // mostly full slots, with a fair share of both sentinels
+            match state % 8 {
+                0 => EMPTY,
+                1 => DELETED,
+                _ => (state >> 8) as u8 & 0x7f,
+            }
+        };
+
+        for _ in 0..1_000 {
+            let ctrl: Vec<u8> = (0..GROUP_WIDTH).map(|_| next_byte()).collect();
+            let group = Group::load(&ctrl, 0);
+            let portable = portable::Group::load(&ctrl, 0);
+
+            for byte in [ctrl[0], ctrl[7], ctrl[15], 0x00, 0x7f] {
+                let expected: Vec<usize> = (0..GROUP_WIDTH).filter(|&i| ctrl[i] == byte).collect();
+                assert_eq!(group.match_byte(byte).collect::<Vec<_>>(), expected);
+                assert_eq!(portable.match_byte(byte), group.match_byte(byte));
+            }
+            assert_eq!(portable.match_empty(), group.match_empty());
+            assert_eq!(portable.match_empty_or_deleted(), group.match_empty_or_deleted());
+        }
+    }
+
+    // sends every key to the same slot, the worst case for any probing scheme
+    #[derive(Default)]
+    struct ConstantHasher;
+
+    impl Hasher for ConstantHasher {
+        fn write(&mut self, _: &[u8]) {}
+
+        fn finish(&self) -> u64 {
+            0
+        }
+    }
+
+    #[test]
+    fn test_with_hasher() {
+        let mut map = HashMap::with_hasher(FxBuildHasher::default());
+        for i in 0..10_000u64 {
+            map.insert(i, i * 3);
+        }
+        for i in 0..10_000u64 {
+            assert_eq!(map.get(&i), Some(&(i * 3)));
+        }
+        assert_eq!(map.get(&10_000), None);
+
+        let map: HashMap<u64, u64, _> = HashMap::with_capacity_and_hasher(100, FxBuildHasher::default());
+        assert!(map.capacity() >= 100);
+        assert_eq!(map.hasher().hash_one(5u64), FxBuildHasher::default().hash_one(5u64));
+
+        let map: HashMap<u64, u64, FxBuildHasher> = HashMap::default();
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn test_seeded_hasher_is_reproducible() {
+        for strategy in [Strategy::Linear, Strategy::RobinHood, Strategy::Swiss] {
+            let build = |seed| {
+                let mut map = HashMap::with_strategy_and_hasher(strategy, SipBuildHasher::new(seed));
+                for i in 0..2_000 {
+                    map.insert(format!("client-{}", i), i);
+                }
+                map
+            };
+
+            // same seed, same layout; a different seed scatters the keys differently
+            assert_eq!(build(1).probe_stats(), build(1).probe_stats());
+            let map = build(2);
+            assert_eq!(map.get("client-1999"), Some(&1_999));
+            assert_eq!(map.len(), 2_000);
+        }
+    }
+
+    #[test]
+    fn test_every_key_colliding() {
+        for strategy in [Strategy::Linear, Strategy::RobinHood, Strategy::Swiss] {
+            let hasher = BuildHasherDefault::<ConstantHasher>::default();
+            let mut map = HashMap::with_strategy_and_hasher(strategy, hasher);
+            for i in 0..300 {
+                assert_eq!(map.insert(i, i), None);
+            }
+            for i in (0..300).step_by(3) {
+                assert_eq!(map.remove(&i), Some(i));
+            }
+            for i in 0..300 {
+                assert_eq!(map.get(&i), if i % 3 == 0 { None } else { Some(&i) });
+            }
+            assert_eq!(map.len(), 200);
+        }
+    }
+
+    #[test]
+    fn test_entry_or_insert() {
+        let mut counts = HashMap::new();
+        for word in ["b", "a", "c", "a", "b", "a"] {
+            *counts.entry(word).or_insert(0) += 1;
+        }
+
+        assert_eq!(counts.get("a"), Some(&3));
+        assert_eq!(counts.get("b"), Some(&2));
+        assert_eq!(counts.get("c"), Some(&1));
+        assert_eq!(counts.len(), 3);
+    }
+
+    #[test]
+    fn test_entry_or_insert_with_and_default() {
+        let mut map: HashMap<i32, Vec<i32>> = HashMap::new();
+        map.entry(1).or_insert_with(|| vec![10]).push(11);
+        map.entry(1).or_insert_with(|| unreachable!()).push(12);
+        map.entry(2).or_default().push(20);
+        map.entry(3).or_insert_with_key(|&key| vec![key * 10]);
+
+        assert_eq!(map.get(&1), Some(&vec![10, 11, 12]));
+        assert_eq!(map.get(&2), Some(&vec![20]));
+        assert_eq!(map.get(&3), Some(&vec![30]));
+    }
+
+    #[test]
+    fn test_entry_and_modify() {
+        let mut map = HashMap::new();
+        map.entry("x").and_modify(|value| *value += 1).or_insert(1);
+        map.entry("x").and_modify(|value| *value += 1).or_insert(1);
+        map.entry("y").and_modify(|value| *value += 1).or_insert(1);
+
+        assert_eq!(map.get("x"), Some(&2));
+        assert_eq!(map.get("y"), Some(&1));
+    }
+
+    #[test]
+    fn test_entry_occupied_and_vacant() {
+        let mut map = HashMap::new();
+        map.insert(30, String::from("30"));
+
+        match map.entry(30) {
+            Entry::Occupied(mut entry) => {
+                assert_eq!(entry.key(), &30);
+                assert_eq!(entry.insert(String::from("thirty")), "30");
+                assert_eq!(entry.get(), "thirty");
+                entry.get_mut().push('!');
+            }
+            Entry::Vacant(_) => panic!("30 should be occupied"),
+        }
+
+        match map.entry(40) {
+            Entry::Vacant(entry) => {
+                assert_eq!(entry.key(), &40);
+                entry.insert(String::from("forty"));
+            }
+            Entry::Occupied(_) => panic!("40 should be vacant"),
+        }
+
+        match map.entry(50) {
+            Entry::Vacant(entry) => assert_eq!(entry.into_key(), 50),
+            Entry::Occupied(_) => panic!("50 should be vacant"),
+        }
+
+        assert_eq!(map.entry(60).key(), &60);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(&30).map(String::as_str), Some("thirty!"));
+        assert_eq!(map.get(&40).map(String::as_str), Some("forty"));
+    }
+
+    #[test]
+    fn test_entry_remove_entry() {
+        let mut map = HashMap::new();
+        for i in 0..10 {
+            map.insert(i, i * 10);
+        }
+
+        if let Entry::Occupied(entry) = map.entry(3) {
+            assert_eq!(entry.remove_entry(), (3, 30));
+        }
+        if let Entry::Occupied(entry) = map.entry(5) {
+            assert_eq!(entry.remove(), 50);
+        }
+
+        assert_eq!(map.len(), 8);
+        assert!(!map.contains_key(&3));
+        assert!(!map.contains_key(&5));
+    }
+
+    #[test]
+    fn test_entry_insert_for_every_strategy() {
+        // the vacant insert has to hand back the new entry's value even when robin hood
+        // displaces other entries or the table grows underneath it
+        for strategy in [Strategy::Linear, Strategy::RobinHood, Strategy::Swiss] {
+            let mut map = HashMap::with_strategy(strategy);
+            for i in 0..5_000 {
+                let value = map.entry(i).or_insert(0);
+                *value = i * 2;
+            }
+            for i in 0..5_000 {
+                assert_eq!(map.get(&i), Some(&(i * 2)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_get_key_value_and_remove_entry() {
+        let mut map = HashMap::new();
+        map.insert(String::from("apple"), 3);
+
+        assert_eq!(map.get_key_value("apple"), Some((&String::from("apple"), &3)));
+        assert_eq!(map.get_key_value("pear"), None);
+
+        assert_eq!(map.remove_entry("apple"), Some((String::from("apple"), 3)));
+        assert_eq!(map.remove_entry("apple"), None);
+        assert!(map.is_empty());
+    }
+
+    // equal and hashed by `key` alone, so a test can tell which copy the map kept
+    #[derive(Debug)]
+    struct Tagged {
+        key: i32,
+        tag: &'static str,
+    }
+
+    impl PartialEq for Tagged {
+        fn eq(&self, other: &Self) -> bool {
+            self.key == other.key
+        }
+    }
+
+    impl Eq for Tagged {}
+
+    impl Hash for Tagged {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            self.key.hash(state);
+        }
+    }
+
+    #[test]
+    fn test_replace_key_or_insert_default() {
+        let mut map = HashMap::new();
+        map.insert(Tagged { key: 1, tag: "first" }, 10);
+
+        // hit: the key is swapped, the value stays
+        let old = map.replace_key_or_insert_default(Tagged { key: 1, tag: "second" });
+        assert_eq!(old.unwrap().tag, "first");
+        let (key, value) = map.get_key_value(&Tagged { key: 1, tag: "" }).unwrap();
+        assert_eq!((key.tag, *value), ("second", 10));
+        assert_eq!(map.len(), 1);
+
+        // miss: the key goes in with a default value
+        assert!(map.replace_key_or_insert_default(Tagged { key: 2, tag: "new" }).is_none());
+        let (key, value) = map.get_key_value(&Tagged { key: 2, tag: "" }).unwrap();
+        assert_eq!((key.tag, *value), ("new", 0));
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn test_iter() {
+        for strategy in [Strategy::Linear, Strategy::RobinHood, Strategy::Swiss] {
+            let mut map = HashMap::with_strategy(strategy);
+            assert_eq!(map.iter().next(), None);
+            for i in 0..100 {
+                map.insert(i, i * i);
+            }
+            for i in (0..100).step_by(4) {
+                map.remove(&i);
+            }
+
+            let iter = map.iter();
+            assert_eq!(iter.len(), 75);
+            let mut entries: Vec<(i32, i32)> = iter.map(|(&key, &value)| (key, value)).collect();
+            entries.sort();
+            let expected: Vec<(i32, i32)> = (0..100).filter(|i| i % 4 != 0).map(|i| (i, i * i)).collect();
+            assert_eq!(entries, expected);
+
+            let mut keys: Vec<i32> = map.keys().copied().collect();
+            keys.sort();
+            assert_eq!(keys, expected.iter().map(|&(key, _)| key).collect::<Vec<_>>());
+            assert_eq!(map.values().sum::<i32>(), expected.iter().map(|&(_, value)| value).sum());
+            assert_eq!((&map).into_iter().count(), 75);
+        }
+    }
+
+    #[test]
+    fn test_incremental_resize_bounds_work_per_operation() {
+        for strategy in [Strategy::Linear, Strategy::RobinHood, Strategy::Swiss] {
+            for step in [1, 3, 16] {
+                let mut map = HashMap::with_strategy(strategy);
+                map.set_incremental_resize(Some(step));
+                let mut reference = std::collections::HashMap::new();
+                let mut resizes = 0;
+
+                let mut state = 0x1234_5678_u64;
+                for i in 0..20_000u64 {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    let key = state % 10_000;
+
+                    let was_resizing = map.is_resizing();
+                    let before = map.moved;
+                    match state % 4 {
+                        0 => assert_eq!(map.remove(&key), reference.remove(&key)),
+                        1 => *map.entry(key).or_insert(0) += 1,
+                        _ => assert_eq!(map.insert(key, i), reference.insert(key, i)),
+                    }
+                    if state % 4 == 1 {
+                        *reference.entry(key).or_insert(0) += 1;
+                    }
+
+                    let moved = map.moved - before;
+                    assert!(moved <= step, "{:?} moved {} entries in one operation", strategy, moved);
+                    if !was_resizing && map.is_resizing() {
+                        resizes += 1;
+                    }
+                    assert_eq!(map.len(), reference.len());
+                }
+
+                assert!(resizes > 3);
+                for key in 0..10_000 {
+                    assert_eq!(map.get(&key), reference.get(&key));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_operations_during_incremental_resize() {
+        for strategy in [Strategy::Linear, Strategy::RobinHood, Strategy::Swiss] {
+            let mut map = HashMap::with_strategy(strategy);
+            map.set_incremental_resize(Some(1));
+            let mut next = 0;
+            while !map.is_resizing() {
+                map.insert(next, next);
+                next += 1;
+            }
+
+            // nearly everything is still in the old table; all of it must stay reachable
+            assert!(map.old.as_ref().unwrap().len > 1);
+            assert_eq!(map.len(), next);
+            assert_eq!(map.iter().count(), next);
+            assert_eq!(map.probe_stats().histogram.iter().sum::<usize>(), next);
+            for key in 0..next {
+                assert_eq!(map.get(&key), Some(&key));
+            }
+
+            *map.get_mut(&0).unwrap() += 100;
+            assert_eq!(map.insert(1, 101), Some(1));
+            assert_eq!(map.remove(&2), Some(2));
+            assert_eq!(map.get(&0), Some(&100));
+            assert_eq!(map.get(&1), Some(&101));
+            assert!(!map.contains_key(&2));
+
+            // keep going until the old table drains, then check the usual invariants hold
+            while map.is_resizing() {
+                map.insert(next, next);
+                next += 1;
+            }
+            assert_eq!(map.len(), next - 1);
+            match strategy {
+                Strategy::RobinHood => assert_robin_hood_invariant(&map),
+                Strategy::Swiss => assert_swiss_invariant(&map),
+                Strategy::Linear => {}
+            }
+        }
+    }
+
+    #[test]
+    fn test_bulk_operations_finish_incremental_resize() {
+        let start_resizing = || {
+            let mut map = HashMap::new();
+            map.set_incremental_resize(Some(2));
+            let mut next = 0;
+            while !map.is_resizing() {
+                map.insert(next, next);
+                next += 1;
+            }
+            (map, next)
+        };
+
+        let (mut map, len) = start_resizing();
+        map.reserve(1);
+        assert!(!map.is_resizing());
+        assert_eq!(map.len(), len);
+
+        let (mut map, len) = start_resizing();
+        map.shrink_to_fit();
+        assert!(!map.is_resizing());
+        assert_eq!(map.len(), len);
+
+        let (mut map, len) = start_resizing();
+        assert_eq!(map.incremental_resize(), Some(2));
+        map.set_incremental_resize(None);
+        assert!(!map.is_resizing());
+        assert_eq!(map.incremental_resize(), None);
+        assert_eq!(map.len(), len);
+        for key in 0..len {
+            assert_eq!(map.get(&key), Some(&key));
+        }
+
+        let (mut map, _) = start_resizing();
+        map.clear();
+        assert!(!map.is_resizing());
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn test_back_to_back_incremental_resizes() {
+        for strategy in [Strategy::Linear, Strategy::RobinHood, Strategy::Swiss] {
+            let mut map = HashMap::with_strategy(strategy);
+            map.set_incremental_resize(Some(1));
+            let mut expected = std::collections::HashMap::new();
+
+            // bursts of inserts followed by bursts of removes keep starting new resizes (growing
+            // ones, and same-size ones to clear tombstones) as soon as the last one finishes
+            let mut next = 0;
+            let mut resizes = 0;
+            for round in 0..40 {
+                for _ in 0..50 + round * 10 {
+                    let was_resizing = map.is_resizing();
+                    assert_eq!(map.insert(next, next), expected.insert(next, next));
+                    resizes += usize::from(!was_resizing && map.is_resizing());
+                    next += 1;
+                }
+                for key in (next - 40 - round * 10..next).step_by(2) {
+                    assert_eq!(map.remove(&key), expected.remove(&key));
+                }
+
+                assert_eq!(map.len(), expected.len());
+                assert_eq!(map.iter().count(), expected.len());
+                for (key, value) in &expected {
+                    assert_eq!(map.get(key), Some(value));
+                }
+            }
+            assert!(resizes > 5);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_incremental_resize_needs_progress() {
+        let mut map: HashMap<i32, i32> = HashMap::new();
+        map.set_incremental_resize(Some(0));
+    }
+}
@@ -0,0 +1,420 @@
+use std::borrow::Borrow;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash};
+use std::iter::Chain;
+
+use crate::hash_map::{HashMap, Keys};
+
+// a HashMap with unit values; everything but the set algebra just forwards to the map
+pub struct HashSet<T, S = RandomState> {
+    map: HashMap<T, (), S>,
+}
+
+impl<T> HashSet<T, RandomState> {
+    pub fn new() -> Self {
+        HashSet { map: HashMap::new() }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        HashSet { map: HashMap::with_capacity(capacity) }
+    }
+}
+
+impl<T, S> HashSet<T, S> {
+    pub fn with_hasher(hash_builder: S) -> Self {
+        HashSet { map: HashMap::with_hasher(hash_builder) }
+    }
+
+    pub fn with_capacity_and_hasher(capacity: usize, hash_builder: S) -> Self {
+        HashSet { map: HashMap::with_capacity_and_hasher(capacity, hash_builder) }
+    }
+
+    pub fn hasher(&self) -> &S {
+        self.map.hasher()
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.map.capacity()
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        self.map.reserve(additional);
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        self.map.shrink_to_fit();
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { keys: self.map.keys() }
+    }
+}
+
+impl<T: Hash + Eq, S: BuildHasher> HashSet<T, S> {
+    // returns false (and drops `value`) if an equal value was already present
+    pub fn insert(&mut self, value: T) -> bool {
+        self.map.insert(value, ()).is_none()
+    }
+
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.contains_key(value)
+    }
+
+    // the stored value equal to `value`
+    pub fn get<Q>(&self, value: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.get_key_value(value).map(|(stored, _)| stored)
+    }
+
+    pub fn remove<Q>(&mut self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.remove(value).is_some()
+    }
+
+    // like remove, but hands back the stored value
+    pub fn take<Q>(&mut self, value: &Q) -> Option<T>
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.remove_entry(value).map(|(stored, _)| stored)
+    }
+
+    // inserts `value`, overwriting and returning an equal value that was already stored
+    pub fn replace(&mut self, value: T) -> Option<T> {
+        // an equal value hashes the same, so it can take the old one's slot in place
+        self.map.replace_key_or_insert_default(value)
+    }
+
+    // every value in either set, each once
+    pub fn union<'a>(&'a self, other: &'a Self) -> Union<'a, T, S> {
+        Union { iter: self.iter().keys.chain(other.difference(self)) }
+    }
+
+    // walks the smaller set and probes the larger one
+    pub fn intersection<'a>(&'a self, other: &'a Self) -> Intersection<'a, T, S> {
+        let (smaller, larger) = if self.len() <= other.len() { (self, other) } else { (other, self) };
+        Intersection { keys: smaller.map.keys(), other: larger }
+    }
+
+    // values in `self` but not in `other`
+    pub fn difference<'a>(&'a self, other: &'a Self) -> Difference<'a, T, S> {
+        Difference { keys: self.map.keys(), other }
+    }
+
+    // values in exactly one of the two sets
+    pub fn symmetric_difference<'a>(&'a self, other: &'a Self) -> SymmetricDifference<'a, T, S> {
+        SymmetricDifference { iter: self.difference(other).chain(other.difference(self)) }
+    }
+
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.len() <= other.len() && self.iter().all(|value| other.contains(value))
+    }
+
+    pub fn is_superset(&self, other: &Self) -> bool {
+        other.is_subset(self)
+    }
+
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+        self.intersection(other).next().is_none()
+    }
+}
+
+impl<T, S: Default> Default for HashSet<T, S> {
+    fn default() -> Self {
+        HashSet { map: HashMap::default() }
+    }
+}
+
+impl<T: Hash + Eq, S: BuildHasher + Default> FromIterator<T> for HashSet<T, S> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut set = HashSet::default();
+        for value in iter {
+            set.insert(value);
+        }
+        set
+    }
+}
+
+impl<'a, T, S> IntoIterator for &'a HashSet<T, S> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct Iter<'a, T> {
+    keys: Keys<'a, T, ()>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        self.keys.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.keys.size_hint()
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+// the set operations are lazy: each value is checked against the other set as it's reached
+
+pub struct Union<'a, T, S = RandomState> {
+    iter: Chain<Keys<'a, T, ()>, Difference<'a, T, S>>,
+}
+
+impl<'a, T: Hash + Eq, S: BuildHasher> Iterator for Union<'a, T, S> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        self.iter.next()
+    }
+}
+
+pub struct Intersection<'a, T, S = RandomState> {
+    keys: Keys<'a, T, ()>,
+    other: &'a HashSet<T, S>,
+}
+
+impl<'a, T: Hash + Eq, S: BuildHasher> Iterator for Intersection<'a, T, S> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        self.keys.by_ref().find(|value| self.other.contains(*value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.keys.size_hint().1)
+    }
+}
+
+pub struct Difference<'a, T, S = RandomState> {
+    keys: Keys<'a, T, ()>,
+    other: &'a HashSet<T, S>,
+}
+
+impl<'a, T: Hash + Eq, S: BuildHasher> Iterator for Difference<'a, T, S> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        self.keys.by_ref().find(|value| !self.other.contains(*value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.keys.size_hint().1)
+    }
+}
+
+pub struct SymmetricDifference<'a, T, S = RandomState> {
+    iter: Chain<Difference<'a, T, S>, Difference<'a, T, S>>,
+}
+
+impl<'a, T: Hash + Eq, S: BuildHasher> Iterator for SymmetricDifference<'a, T, S> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        self.iter.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hasher::FxBuildHasher;
+
+    fn sorted<'a>(values: impl Iterator<Item = &'a i32>) -> Vec<i32> {
+        let mut values: Vec<i32> = values.copied().collect();
+        values.sort();
+        values
+    }
+
+    #[test]
+    fn test_new_set_is_empty() {
+        let set: HashSet<i32> = HashSet::new();
+        assert!(set.is_empty());
+        assert_eq!(set.len(), 0);
+        assert!(!set.contains(&1));
+        assert_eq!(set.iter().next(), None);
+    }
+
+    #[test]
+    fn test_insert_and_contains() {
+        let mut set = HashSet::new();
+        assert!(set.insert(1));
+        assert!(set.insert(2));
+        assert!(!set.insert(1));
+
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(&1));
+        assert!(set.contains(&2));
+        assert!(!set.contains(&3));
+    }
+
+    #[test]
+    fn test_remove_and_take() {
+        let mut set: HashSet<String> = ["apple", "pear", "plum"].into_iter().map(String::from).collect();
+
+        assert!(set.remove("apple"));
+        assert!(!set.remove("apple"));
+        assert_eq!(set.take("pear"), Some(String::from("pear")));
+        assert_eq!(set.take("pear"), None);
+
+        assert_eq!(set.len(), 1);
+        assert!(set.contains("plum"));
+    }
+
+    // equal under Eq and Hash, but still distinguishable, to see which copy the set keeps
+    #[derive(Debug)]
+    struct Tagged {
+        key: i32,
+        tag: &'static str,
+    }
+
+    impl PartialEq for Tagged {
+        fn eq(&self, other: &Self) -> bool {
+            self.key == other.key
+        }
+    }
+
+    impl Eq for Tagged {}
+
+    impl Hash for Tagged {
+        fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+            self.key.hash(state);
+        }
+    }
+
+    #[test]
+    fn test_get_and_replace() {
+        let mut set = HashSet::new();
+        set.insert(Tagged { key: 1, tag: "first" });
+
+        assert!(!set.insert(Tagged { key: 1, tag: "ignored" }));
+        assert_eq!(set.get(&Tagged { key: 1, tag: "" }).unwrap().tag, "first");
+
+        let old = set.replace(Tagged { key: 1, tag: "second" });
+        assert_eq!(old.unwrap().tag, "first");
+        assert_eq!(set.get(&Tagged { key: 1, tag: "" }).unwrap().tag, "second");
+
+        assert!(set.replace(Tagged { key: 2, tag: "new" }).is_none());
+        assert_eq!(set.len(), 2);
+        assert!(set.get(&Tagged { key: 3, tag: "" }).is_none());
+    }
+
+    #[test]
+    fn test_iter() {
+        let set: HashSet<i32> = (0..50).collect();
+        assert_eq!(set.iter().len(), 50);
+        assert_eq!(sorted(set.iter()), (0..50).collect::<Vec<_>>());
+        assert_eq!(sorted((&set).into_iter()), (0..50).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let a: HashSet<i32> = [1, 2, 3, 4, 5].into_iter().collect();
+        let b: HashSet<i32> = [4, 5, 6, 7].into_iter().collect();
+
+        assert_eq!(sorted(a.union(&b)), vec![1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(sorted(a.intersection(&b)), vec![4, 5]);
+        assert_eq!(sorted(b.intersection(&a)), vec![4, 5]);
+        assert_eq!(sorted(a.difference(&b)), vec![1, 2, 3]);
+        assert_eq!(sorted(b.difference(&a)), vec![6, 7]);
+        assert_eq!(sorted(a.symmetric_difference(&b)), vec![1, 2, 3, 6, 7]);
+
+        let empty = HashSet::new();
+        assert_eq!(sorted(a.union(&empty)), vec![1, 2, 3, 4, 5]);
+        assert_eq!(a.intersection(&empty).count(), 0);
+        assert_eq!(sorted(a.difference(&empty)), vec![1, 2, 3, 4, 5]);
+        assert_eq!(empty.difference(&a).count(), 0);
+    }
+
+    #[test]
+    fn test_set_operations_are_lazy() {
+        let a: HashSet<i32> = (0..100_000).collect();
+        let b: HashSet<i32> = (50_000..150_000).collect();
+
+        // taking a few items only checks a few values against the other set
+        assert_eq!(a.union(&b).take(3).count(), 3);
+        assert!(a.intersection(&b).take(5).all(|value| *value >= 50_000 && *value < 100_000));
+        assert!(a.difference(&b).take(5).all(|value| *value < 50_000));
+        assert_eq!(a.symmetric_difference(&b).count(), 100_000);
+    }
+
+    #[test]
+    fn test_subset_superset_disjoint() {
+        let small: HashSet<i32> = [2, 3].into_iter().collect();
+        let large: HashSet<i32> = [1, 2, 3, 4].into_iter().collect();
+        let other: HashSet<i32> = [5, 6].into_iter().collect();
+        let empty = HashSet::new();
+
+        assert!(small.is_subset(&large));
+        assert!(!large.is_subset(&small));
+        assert!(large.is_superset(&small));
+        assert!(!small.is_superset(&large));
+        assert!(small.is_subset(&small));
+        assert!(empty.is_subset(&small));
+
+        assert!(small.is_disjoint(&other));
+        assert!(!small.is_disjoint(&large));
+        assert!(empty.is_disjoint(&empty));
+    }
+
+    #[test]
+    fn test_clear_reserve_and_shrink() {
+        let mut set = HashSet::with_capacity(10);
+        assert!(set.capacity() >= 10);
+        for i in 0..1_000 {
+            set.insert(i);
+        }
+        set.clear();
+        assert!(set.is_empty());
+        assert!(!set.contains(&5));
+
+        set.shrink_to_fit();
+        assert_eq!(set.capacity(), 0);
+        set.reserve(50);
+        assert!(set.capacity() >= 50);
+    }
+
+    #[test]
+    fn test_with_hasher() {
+        let mut set = HashSet::with_hasher(FxBuildHasher::default());
+        for i in 0..1_000u64 {
+            set.insert(i * 7);
+        }
+        assert!(set.contains(&700));
+        assert!(!set.contains(&701));
+
+        let other: HashSet<u64, FxBuildHasher> = (0..100).map(|i| i * 7).collect();
+        assert!(other.is_subset(&set));
+        assert_eq!(set.difference(&other).count(), 900);
+    }
+}
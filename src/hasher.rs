@@ -0,0 +1,270 @@
+use std::hash::{BuildHasher, BuildHasherDefault, Hasher};
+
+// SipHash-1-3 (one compression round per word, three finalization rounds), the same
+// function std uses for its default hasher. keyed with 128 secret bits, which makes the
+// hashes of attacker-chosen keys unpredictable and so protects maps against collision floods
+#[derive(Clone, Debug)]
+pub struct SipHasher13 {
+    v0: u64,
+    v1: u64,
+    v2: u64,
+    v3: u64,
+    tail: u64,    // bytes not yet forming a whole word, little-endian
+    ntail: usize, // how many bytes `tail` holds
+    length: usize,
+}
+
+impl SipHasher13 {
+    pub fn new_with_keys(k0: u64, k1: u64) -> Self {
+        SipHasher13 {
+            v0: k0 ^ 0x736f_6d65_7073_6575,
+            v1: k1 ^ 0x646f_7261_6e64_6f6d,
+            v2: k0 ^ 0x6c79_6765_6e65_7261,
+            v3: k1 ^ 0x7465_6462_7974_6573,
+            tail: 0,
+            ntail: 0,
+            length: 0,
+        }
+    }
+
+    fn round(&mut self) {
+        self.v0 = self.v0.wrapping_add(self.v1);
+        self.v1 = self.v1.rotate_left(13) ^ self.v0;
+        self.v0 = self.v0.rotate_left(32);
+        self.v2 = self.v2.wrapping_add(self.v3);
+        self.v3 = self.v3.rotate_left(16) ^ self.v2;
+        self.v0 = self.v0.wrapping_add(self.v3);
+        self.v3 = self.v3.rotate_left(21) ^ self.v0;
+        self.v2 = self.v2.wrapping_add(self.v1);
+        self.v1 = self.v1.rotate_left(17) ^ self.v2;
+        self.v2 = self.v2.rotate_left(32);
+    }
+
+    fn compress(&mut self, word: u64) {
+        self.v3 ^= word;
+        self.round();
+        self.v0 ^= word;
+    }
+}
+
+impl Hasher for SipHasher13 {
+    fn write(&mut self, bytes: &[u8]) {
+        self.length += bytes.len();
+        let mut rest = bytes;
+
+        // top up a partial word left over from the previous write first
+        if self.ntail != 0 {
+            let needed = (8 - self.ntail).min(rest.len());
+            for &byte in &rest[..needed] {
+                self.tail |= (byte as u64) << (8 * self.ntail);
+                self.ntail += 1;
+            }
+            rest = &rest[needed..];
+            if self.ntail < 8 {
+                return;
+            }
+            self.compress(self.tail);
+            self.tail = 0;
+            self.ntail = 0;
+        }
+
+        let mut words = rest.chunks_exact(8);
+        for word in &mut words {
+            self.compress(u64::from_le_bytes(word.try_into().unwrap()));
+        }
+        for &byte in words.remainder() {
+            self.tail |= (byte as u64) << (8 * self.ntail);
+            self.ntail += 1;
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        let mut state = self.clone();
+        // the last word carries the low byte of the total length in its top byte
+        let last = ((self.length as u64 & 0xff) << 56) | self.tail;
+        state.compress(last);
+
+        state.v2 ^= 0xff;
+        for _ in 0..3 {
+            state.round();
+        }
+        state.v0 ^ state.v1 ^ state.v2 ^ state.v3
+    }
+}
+
+// builds SipHasher13s keyed from a seed. the seed is the caller's to choose; to actually
+// resist flooding it should come from a source the attacker can't observe or guess,
+// e.g. the operating system's random number generator
+#[derive(Clone, Copy, Debug)]
+pub struct SipBuildHasher {
+    k0: u64,
+    k1: u64,
+}
+
+impl SipBuildHasher {
+    // spreads the 64-bit seed into the two 64-bit keys
+    pub fn new(seed: u64) -> Self {
+        let mut state = seed;
+        SipBuildHasher {
+            k0: split_mix(&mut state),
+            k1: split_mix(&mut state),
+        }
+    }
+
+    pub fn with_keys(k0: u64, k1: u64) -> Self {
+        SipBuildHasher { k0, k1 }
+    }
+}
+
+impl BuildHasher for SipBuildHasher {
+    type Hasher = SipHasher13;
+
+    fn build_hasher(&self) -> SipHasher13 {
+        SipHasher13::new_with_keys(self.k0, self.k1)
+    }
+}
+
+// SplitMix64, a tiny generator whose outputs are well mixed even for adjacent seeds
+fn split_mix(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+// the multiply-rotate hash from Firefox and rustc: one multiplication per word, so it is
+// very fast for integer keys, but unkeyed and easy to force collisions on. only use it for
+// keys that don't come from untrusted input
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FxHasher {
+    hash: u64,
+}
+
+const FX_SEED: u64 = 0x517c_c1b7_2722_0a95;
+
+impl FxHasher {
+    fn add_to_hash(&mut self, word: u64) {
+        self.hash = (self.hash.rotate_left(5) ^ word).wrapping_mul(FX_SEED);
+    }
+}
+
+impl Hasher for FxHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        let mut words = bytes.chunks_exact(8);
+        for word in &mut words {
+            self.add_to_hash(u64::from_le_bytes(word.try_into().unwrap()));
+        }
+
+        let mut rest = words.remainder();
+        if rest.len() >= 4 {
+            self.add_to_hash(u32::from_le_bytes(rest[..4].try_into().unwrap()) as u64);
+            rest = &rest[4..];
+        }
+        if rest.len() >= 2 {
+            self.add_to_hash(u16::from_le_bytes(rest[..2].try_into().unwrap()) as u64);
+            rest = &rest[2..];
+        }
+        if let Some(&byte) = rest.first() {
+            self.add_to_hash(byte as u64);
+        }
+    }
+
+    // integers skip the byte slicing entirely
+    fn write_u8(&mut self, i: u8) {
+        self.add_to_hash(i as u64);
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.add_to_hash(i as u64);
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.add_to_hash(i as u64);
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.add_to_hash(i);
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.add_to_hash(i as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+pub type FxBuildHasher = BuildHasherDefault<FxHasher>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::Hash;
+
+    fn sip(k0: u64, k1: u64, chunks: &[&[u8]]) -> u64 {
+        let mut hasher = SipHasher13::new_with_keys(k0, k1);
+        for chunk in chunks {
+            hasher.write(chunk);
+        }
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_sip_matches_std() {
+        // std's DefaultHasher::new() is SipHash-1-3 with both keys zero
+        let bytes: Vec<u8> = (0..64).collect();
+        for len in 0..=bytes.len() {
+            let mut expected = DefaultHasher::new();
+            expected.write(&bytes[..len]);
+            assert_eq!(sip(0, 0, &[&bytes[..len]]), expected.finish(), "length {}", len);
+        }
+    }
+
+    #[test]
+    fn test_sip_split_writes() {
+        // the hash depends only on the byte stream, not on how it was split into writes
+        let bytes: Vec<u8> = (0..40).map(|i| i * 5).collect();
+        let whole = sip(1, 2, &[&bytes]);
+        for split in 0..bytes.len() {
+            let (a, b) = bytes.split_at(split);
+            assert_eq!(sip(1, 2, &[a, b]), whole);
+        }
+        assert_eq!(sip(1, 2, &[&bytes[..3], &bytes[3..5], &bytes[5..17], &bytes[17..]]), whole);
+    }
+
+    #[test]
+    fn test_sip_keys_change_the_hash() {
+        let message: &[u8] = b"the same message";
+        assert_ne!(sip(0, 0, &[message]), sip(1, 0, &[message]));
+        assert_ne!(sip(0, 0, &[message]), sip(0, 1, &[message]));
+
+        let a = SipBuildHasher::new(42);
+        let b = SipBuildHasher::new(43);
+        assert_eq!(a.hash_one("key"), SipBuildHasher::new(42).hash_one("key"));
+        assert_ne!(a.hash_one("key"), b.hash_one("key"));
+
+        let mut expected = DefaultHasher::new();
+        "key".hash(&mut expected);
+        assert_eq!(SipBuildHasher::with_keys(0, 0).hash_one("key"), expected.finish());
+    }
+
+    #[test]
+    fn test_fx_hasher() {
+        let build = FxBuildHasher::default();
+        assert_eq!(build.hash_one(12345u64), build.hash_one(12345u64));
+        assert_eq!(build.hash_one(0u64), 0);
+        assert_eq!(build.hash_one(1u64), FX_SEED);
+
+        // distinct small integers never collide, since multiplying by an odd constant is a bijection
+        let mut hashes: Vec<u64> = (0..10_000u64).map(|i| build.hash_one(i)).collect();
+        hashes.sort_unstable();
+        hashes.dedup();
+        assert_eq!(hashes.len(), 10_000);
+
+        assert_ne!(build.hash_one("abc"), build.hash_one("abd"));
+        assert_ne!(build.hash_one([1u8, 2, 3, 4, 5, 6, 7]), build.hash_one([1u8, 2, 3, 4, 5, 6, 8]));
+    }
+}
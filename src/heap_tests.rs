@@ -0,0 +1,363 @@
+// one test suite for every heap in the crate. each heap is driven through the same trait in
+// min-first order and checked against a sorted model, so they all have to agree on behaviour
+
+use crate::compare::Reversed;
+use crate::handle::{Handle, NodeHandle};
+use crate::{BinomialHeap, FibonacciHeap, IndexedPriorityQueue, PairingHeap, PriorityQueue};
+
+trait MinHeap {
+    type Handle: Clone;
+
+    fn create() -> Self;
+    fn push(&mut self, value: i64) -> Self::Handle;
+    fn pop(&mut self) -> Option<i64>;
+    fn peek(&self) -> Option<&i64>;
+    fn size(&self) -> usize;
+}
+
+trait Addressable: MinHeap {
+    fn get(&self, handle: &Self::Handle) -> Option<&i64>;
+    fn contains(&self, handle: &Self::Handle) -> bool;
+    fn decrease_key(&mut self, handle: &Self::Handle, value: i64) -> Option<i64>;
+    fn remove(&mut self, handle: &Self::Handle) -> Option<i64>;
+}
+
+trait Meldable: Addressable {
+    fn merge(&mut self, other: Self);
+}
+
+impl MinHeap for PriorityQueue<i64, Reversed> {
+    type Handle = ();
+
+    fn create() -> Self {
+        PriorityQueue::min()
+    }
+
+    fn push(&mut self, value: i64) {
+        self.push(value);
+    }
+
+    fn pop(&mut self) -> Option<i64> {
+        self.pop()
+    }
+
+    fn peek(&self) -> Option<&i64> {
+        self.peek()
+    }
+
+    fn size(&self) -> usize {
+        self.size()
+    }
+}
+
+impl MinHeap for IndexedPriorityQueue<i64, Reversed> {
+    type Handle = Handle;
+
+    fn create() -> Self {
+        IndexedPriorityQueue::min()
+    }
+
+    fn push(&mut self, value: i64) -> Handle {
+        self.push(value)
+    }
+
+    fn pop(&mut self) -> Option<i64> {
+        self.pop()
+    }
+
+    fn peek(&self) -> Option<&i64> {
+        self.peek()
+    }
+
+    fn size(&self) -> usize {
+        self.size()
+    }
+}
+
+impl Addressable for IndexedPriorityQueue<i64, Reversed> {
+    fn get(&self, handle: &Handle) -> Option<&i64> {
+        self.get(*handle)
+    }
+
+    fn contains(&self, handle: &Handle) -> bool {
+        self.contains(*handle)
+    }
+
+    fn decrease_key(&mut self, handle: &Handle, value: i64) -> Option<i64> {
+        assert!(value <= *self.get(*handle)?);
+        self.change_priority(*handle, value)
+    }
+
+    fn remove(&mut self, handle: &Handle) -> Option<i64> {
+        self.remove(*handle)
+    }
+}
+
+// the node-based heaps all share one shape of API, give or take the name of remove
+macro_rules! impl_node_heap {
+    ($heap:ty, $create:path, $remove:ident) => {
+        impl MinHeap for $heap {
+            type Handle = NodeHandle;
+
+            fn create() -> Self {
+                $create()
+            }
+
+            fn push(&mut self, value: i64) -> NodeHandle {
+                self.push(value)
+            }
+
+            fn pop(&mut self) -> Option<i64> {
+                self.pop()
+            }
+
+            fn peek(&self) -> Option<&i64> {
+                self.peek()
+            }
+
+            fn size(&self) -> usize {
+                self.size()
+            }
+        }
+
+        impl Addressable for $heap {
+            fn get(&self, handle: &NodeHandle) -> Option<&i64> {
+                self.get(handle)
+            }
+
+            fn contains(&self, handle: &NodeHandle) -> bool {
+                self.contains(handle)
+            }
+
+            fn decrease_key(&mut self, handle: &NodeHandle, value: i64) -> Option<i64> {
+                self.decrease_key(handle, value)
+            }
+
+            fn remove(&mut self, handle: &NodeHandle) -> Option<i64> {
+                self.$remove(handle)
+            }
+        }
+
+        impl Meldable for $heap {
+            fn merge(&mut self, other: Self) {
+                self.merge(other)
+            }
+        }
+    };
+}
+
+impl_node_heap!(PairingHeap<i64, Reversed>, PairingHeap::min, remove);
+impl_node_heap!(BinomialHeap<i64, Reversed>, BinomialHeap::min, remove);
+impl_node_heap!(FibonacciHeap<i64, Reversed>, FibonacciHeap::min, delete);
+
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+
+    fn value(&mut self) -> i64 {
+        self.below(1_000) as i64 - 500
+    }
+}
+
+fn drain<H: MinHeap>(heap: &mut H) -> Vec<i64> {
+    std::iter::from_fn(|| heap.pop()).collect()
+}
+
+fn check_empty<H: MinHeap>() {
+    let mut heap = H::create();
+    assert_eq!(heap.size(), 0);
+    assert_eq!(heap.peek(), None);
+    assert_eq!(heap.pop(), None);
+}
+
+fn check_sorts<H: MinHeap>() {
+    let mut rng = Rng(0x1234_5678_9abc_def0);
+    let mut heap = H::create();
+    let mut values: Vec<i64> = (0..2_000).map(|_| rng.value()).collect();
+    for &value in &values {
+        heap.push(value);
+    }
+    assert_eq!(heap.size(), values.len());
+
+    values.sort_unstable();
+    assert_eq!(heap.peek(), values.first());
+    assert_eq!(drain(&mut heap), values);
+    assert_eq!(heap.size(), 0);
+}
+
+fn check_interleaved<H: MinHeap>() {
+    let mut rng = Rng(0x0bad_cafe_dead_beef);
+    let mut heap = H::create();
+    let mut model: Vec<i64> = Vec::new();
+    for _ in 0..5_000 {
+        if rng.below(3) < 2 {
+            let value = rng.value();
+            heap.push(value);
+            model.push(value);
+        } else {
+            model.sort_unstable_by(|a, b| b.cmp(a));
+            assert_eq!(heap.pop(), model.pop());
+        }
+        assert_eq!(heap.size(), model.len());
+        assert_eq!(heap.peek(), model.iter().min());
+    }
+}
+
+fn check_handles<H: Addressable>() {
+    let mut rng = Rng(0x5eed_5eed_5eed_5eed);
+    let mut heap = H::create();
+    let mut live: Vec<(H::Handle, i64)> = Vec::new();
+    let mut dead: Vec<H::Handle> = Vec::new();
+    for _ in 0..5_000 {
+        match rng.below(6) {
+            0 | 1 => {
+                let value = rng.value();
+                live.push((heap.push(value), value));
+            }
+            2 | 3 if !live.is_empty() => {
+                let i = rng.below(live.len());
+                let value = live[i].1 - rng.below(100) as i64;
+                assert_eq!(heap.decrease_key(&live[i].0, value), Some(live[i].1));
+                live[i].1 = value;
+            }
+            4 if !live.is_empty() => {
+                let (handle, value) = live.swap_remove(rng.below(live.len()));
+                assert_eq!(heap.remove(&handle), Some(value));
+                dead.push(handle);
+            }
+            _ => {
+                let min = live.iter().map(|&(_, value)| value).min();
+                assert_eq!(heap.pop(), min);
+                if min.is_some() {
+                    let i = live.iter().position(|(handle, _)| !heap.contains(handle)).unwrap();
+                    dead.push(live.swap_remove(i).0);
+                }
+            }
+        }
+        assert_eq!(heap.size(), live.len());
+    }
+
+    for (handle, value) in &live {
+        assert_eq!(heap.get(handle), Some(value));
+    }
+    for handle in &dead {
+        assert!(!heap.contains(handle));
+        assert_eq!(heap.get(handle), None);
+        assert_eq!(heap.decrease_key(handle, i64::MIN), None);
+        assert_eq!(heap.remove(handle), None);
+    }
+}
+
+fn check_merge<H: Meldable>() {
+    let mut rng = Rng(0xfeed_face_0123_4567);
+    let mut heaps: Vec<H> = Vec::new();
+    let mut handles: Vec<(H::Handle, i64)> = Vec::new();
+    for _ in 0..8 {
+        let mut heap = H::create();
+        for _ in 0..rng.below(200) {
+            let value = rng.value();
+            handles.push((heap.push(value), value));
+        }
+        heaps.push(heap);
+    }
+
+    // merge pairwise, so some items move through several merges
+    while heaps.len() > 1 {
+        let other = heaps.pop().unwrap();
+        let i = rng.below(heaps.len());
+        heaps[i].merge(other);
+    }
+    let mut heap = heaps.pop().unwrap();
+    assert_eq!(heap.size(), handles.len());
+
+    // handles into the merged-away heaps still work
+    for (handle, value) in handles.iter_mut().step_by(3) {
+        assert_eq!(heap.get(handle), Some(&*value));
+        *value -= 1_000;
+        heap.decrease_key(handle, *value);
+    }
+    for (handle, value) in handles.iter().skip(1).step_by(5) {
+        assert_eq!(heap.remove(handle), Some(*value));
+    }
+
+    let mut expected: Vec<i64> = handles
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| i % 5 != 1)
+        .map(|(_, &(_, value))| value)
+        .collect();
+    expected.sort_unstable();
+    assert_eq!(drain(&mut heap), expected);
+
+    // merging with empty heaps either way round
+    let mut empty = H::create();
+    let mut one = H::create();
+    one.push(7);
+    empty.merge(H::create());
+    empty.merge(one);
+    assert_eq!(empty.pop(), Some(7));
+    assert_eq!(empty.pop(), None);
+}
+
+macro_rules! heap_suite {
+    ($name:ident, $heap:ty) => {
+        mod $name {
+            use super::*;
+
+            #[test]
+            fn test_empty() {
+                check_empty::<$heap>();
+            }
+
+            #[test]
+            fn test_sorts() {
+                check_sorts::<$heap>();
+            }
+
+            #[test]
+            fn test_interleaved() {
+                check_interleaved::<$heap>();
+            }
+        }
+    };
+    ($name:ident, $heap:ty, addressable) => {
+        mod $name {
+            use super::*;
+
+            heap_suite!(basic, $heap);
+
+            #[test]
+            fn test_handles() {
+                check_handles::<$heap>();
+            }
+        }
+    };
+    ($name:ident, $heap:ty, meldable) => {
+        mod $name {
+            use super::*;
+
+            heap_suite!(addressable, $heap, addressable);
+
+            #[test]
+            fn test_merge() {
+                check_merge::<$heap>();
+            }
+        }
+    };
+}
+
+heap_suite!(priority_queue, PriorityQueue<i64, Reversed>);
+heap_suite!(indexed_priority_queue, IndexedPriorityQueue<i64, Reversed>, addressable);
+heap_suite!(pairing_heap, PairingHeap<i64, Reversed>, meldable);
+heap_suite!(binomial_heap, BinomialHeap<i64, Reversed>, meldable);
+heap_suite!(fibonacci_heap, FibonacciHeap<i64, Reversed>, meldable);
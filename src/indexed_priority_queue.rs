@@ -0,0 +1,361 @@
+use std::cmp::Ordering;
+
+use crate::compare::{ByKey, Compare, Natural, Reversed};
+use crate::handle::{Handle, Slab};
+
+// a binary heap like PriorityQueue whose items can be found again after they're pushed:
+// push hands back a Handle, and the slab behind the handles records where in the heap each
+// item currently sits. every swap in every sift updates both positions, which is what lets
+// change_priority and remove start sifting from the right place in O(log n)
+pub struct IndexedPriorityQueue<T, C = Natural> {
+    heap: Vec<(usize, T)>, // each item with the index of its slab slot
+    positions: Slab<usize>,
+    cmp: C,
+}
+
+impl<T: Ord> IndexedPriorityQueue<T> {
+    pub fn new() -> Self {
+        Self::with_comparator(Natural)
+    }
+
+    pub fn max() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord> IndexedPriorityQueue<T, Reversed> {
+    // pops the smallest item first, the usual order for Dijkstra and A*
+    pub fn min() -> Self {
+        Self::with_comparator(Reversed(Natural))
+    }
+}
+
+impl<T, F> IndexedPriorityQueue<T, ByKey<F>> {
+    pub fn with_key<K: Ord>(key: F) -> Self
+    where
+        F: Fn(&T) -> K,
+    {
+        Self::with_comparator(ByKey(key))
+    }
+}
+
+impl<T, F> IndexedPriorityQueue<T, Reversed<ByKey<F>>> {
+    pub fn min_by_key<K: Ord>(key: F) -> Self
+    where
+        F: Fn(&T) -> K,
+    {
+        Self::with_comparator(Reversed(ByKey(key)))
+    }
+}
+
+impl<T, C> IndexedPriorityQueue<T, C> {
+    pub fn with_comparator(cmp: C) -> Self {
+        IndexedPriorityQueue {
+            heap: Vec::new(),
+            positions: Slab::new(),
+            cmp,
+        }
+    }
+
+    pub fn peek(&self) -> Option<&T> {
+        self.heap.first().map(|(_, value)| value)
+    }
+
+    pub fn peek_handle(&self) -> Option<Handle> {
+        self.heap.first().map(|&(slot, _)| self.positions.handle(slot))
+    }
+
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        let &position = self.positions.get(handle)?;
+        Some(&self.heap[position].1)
+    }
+
+    // false once the item has been popped or removed
+    pub fn contains(&self, handle: Handle) -> bool {
+        self.positions.contains(handle)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    pub fn size(&self) -> usize {
+        self.heap.len()
+    }
+
+    // invalidates every handle
+    pub fn clear(&mut self) {
+        self.heap.clear();
+        self.positions.clear();
+    }
+
+    fn swap(&mut self, a: usize, b: usize) {
+        self.heap.swap(a, b);
+        *self.positions.at_mut(self.heap[a].0) = a;
+        *self.positions.at_mut(self.heap[b].0) = b;
+    }
+}
+
+impl<T, C: Compare<T>> IndexedPriorityQueue<T, C> {
+    pub fn push(&mut self, value: T) -> Handle {
+        let position = self.heap.len();
+        let handle = self.positions.insert(position);
+        self.heap.push((handle.index(), value));
+        self.sift_up(position);
+        handle
+    }
+
+    // removes and returns the greatest item under the comparator
+    pub fn pop(&mut self) -> Option<T> {
+        let handle = self.peek_handle()?;
+        self.remove(handle)
+    }
+
+    // replaces the item's value, which may raise or lower its priority, and returns the old
+    // one. None, leaving the queue untouched, if the handle is stale
+    pub fn change_priority(&mut self, handle: Handle, value: T) -> Option<T> {
+        let &position = self.positions.get(handle)?;
+        let old = std::mem::replace(&mut self.heap[position].1, value);
+        self.restore(position);
+        Some(old)
+    }
+
+    pub fn remove(&mut self, handle: Handle) -> Option<T> {
+        let &position = self.positions.get(handle)?;
+        // move the item to the end, where taking it out disturbs nothing
+        let last = self.heap.len() - 1;
+        self.swap(position, last);
+        let (_, value) = self.heap.pop().unwrap();
+        self.positions.remove(handle);
+
+        // the former last item now fills the hole and may belong above or below it
+        if position < self.heap.len() {
+            self.restore(position);
+        }
+        Some(value)
+    }
+
+    // sifts the item at `position` whichever way its priority calls for
+    fn restore(&mut self, position: usize) {
+        if self.sift_up(position) == position {
+            self.sift_down(position);
+        }
+    }
+
+    // returns where the item ended up
+    fn sift_up(&mut self, mut index: usize) -> usize {
+        while index > 0 {
+            let parent = (index - 1) / 2;
+            if !self.greater(index, parent) {
+                break;
+            }
+            self.swap(index, parent);
+            index = parent;
+        }
+        index
+    }
+
+    fn sift_down(&mut self, mut index: usize) {
+        let end = self.heap.len();
+        loop {
+            let left = 2 * index + 1;
+            if left >= end {
+                break;
+            }
+
+            let right = left + 1;
+            let larger = if right < end && self.greater(right, left) { right } else { left };
+            if !self.greater(larger, index) {
+                break;
+            }
+            self.swap(index, larger);
+            index = larger;
+        }
+    }
+
+    fn greater(&self, a: usize, b: usize) -> bool {
+        self.cmp.compare(&self.heap[a].1, &self.heap[b].1) == Ordering::Greater
+    }
+}
+
+impl<T, C: Default> Default for IndexedPriorityQueue<T, C> {
+    fn default() -> Self {
+        Self::with_comparator(C::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the heap order holds and every item's recorded position is where it really is
+    fn assert_consistent<T, C: Compare<T>>(queue: &IndexedPriorityQueue<T, C>) {
+        for (index, &(slot, _)) in queue.heap.iter().enumerate() {
+            assert_eq!(queue.positions.get(queue.positions.handle(slot)), Some(&index));
+            if index > 0 {
+                assert!(!queue.greater(index, (index - 1) / 2));
+            }
+        }
+    }
+
+    #[test]
+    fn test_push_pop_and_handles() {
+        let mut queue = IndexedPriorityQueue::new();
+        assert!(queue.is_empty());
+        assert_eq!(queue.pop(), None);
+
+        let handles: Vec<Handle> = [5, 1, 8, 3].into_iter().map(|value| queue.push(value)).collect();
+        assert_consistent(&queue);
+        assert_eq!(queue.size(), 4);
+        assert_eq!(queue.peek(), Some(&8));
+        assert_eq!(queue.peek_handle(), Some(handles[2]));
+
+        // handles follow their items as the heap rearranges
+        for (handle, value) in handles.iter().zip([5, 1, 8, 3]) {
+            assert_eq!(queue.get(*handle), Some(&value));
+        }
+
+        assert_eq!(queue.pop(), Some(8));
+        assert!(!queue.contains(handles[2]));
+        assert_eq!(queue.get(handles[2]), None);
+        assert!(queue.contains(handles[0]));
+        assert_consistent(&queue);
+    }
+
+    #[test]
+    fn test_change_priority() {
+        let mut queue = IndexedPriorityQueue::min();
+        let a = queue.push(10);
+        let b = queue.push(20);
+        let c = queue.push(30);
+
+        // decrease-key
+        assert_eq!(queue.change_priority(c, 5), Some(30));
+        assert_eq!(queue.peek_handle(), Some(c));
+        assert_consistent(&queue);
+
+        // increase-key
+        assert_eq!(queue.change_priority(c, 25), Some(5));
+        assert_eq!(queue.peek_handle(), Some(a));
+        assert_consistent(&queue);
+
+        assert_eq!(queue.pop(), Some(10));
+        assert_eq!(queue.change_priority(a, 1), None);
+        assert_eq!(queue.pop(), Some(20));
+        assert_eq!(queue.get(b), None);
+        assert_eq!(queue.pop(), Some(25));
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut queue = IndexedPriorityQueue::new();
+        let handles: Vec<Handle> = (0..10).map(|value| queue.push(value)).collect();
+
+        assert_eq!(queue.remove(handles[4]), Some(4));
+        assert_eq!(queue.remove(handles[4]), None);
+        assert_eq!(queue.remove(handles[9]), Some(9));
+        assert_eq!(queue.remove(handles[0]), Some(0));
+        assert_consistent(&queue);
+
+        let rest: Vec<_> = std::iter::from_fn(|| queue.pop()).collect();
+        assert_eq!(rest, vec![8, 7, 6, 5, 3, 2, 1]);
+    }
+
+    #[test]
+    fn test_stale_handles_do_not_reach_new_items() {
+        let mut queue = IndexedPriorityQueue::new();
+        let old = queue.push(1);
+        queue.pop();
+        let new = queue.push(2);
+
+        assert!(!queue.contains(old));
+        assert_eq!(queue.change_priority(old, 100), None);
+        assert_eq!(queue.remove(old), None);
+        assert_eq!(queue.get(new), Some(&2));
+
+        queue.clear();
+        assert!(queue.is_empty());
+        assert!(!queue.contains(new));
+    }
+
+    #[test]
+    fn test_random_operations_keep_positions_in_sync() {
+        let mut queue = IndexedPriorityQueue::new();
+        let mut live: Vec<(Handle, u32)> = Vec::new();
+        let mut state = 0xdead_beefu32;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state
+        };
+
+        for _ in 0..5_000 {
+            let roll = next();
+            match roll % 5 {
+                0 | 1 => {
+                    let value = next() % 1_000;
+                    live.push((queue.push(value), value));
+                }
+                2 if !live.is_empty() => {
+                    let i = next() as usize % live.len();
+                    let value = next() % 1_000;
+                    assert_eq!(queue.change_priority(live[i].0, value), Some(live[i].1));
+                    live[i].1 = value;
+                }
+                3 if !live.is_empty() => {
+                    let (handle, value) = live.swap_remove(next() as usize % live.len());
+                    assert_eq!(queue.remove(handle), Some(value));
+                }
+                _ => {
+                    let max = live.iter().map(|&(_, value)| value).max();
+                    assert_eq!(queue.pop(), max);
+                    if let Some(max) = max {
+                        let i = live.iter().position(|&(handle, value)| value == max && !queue.contains(handle));
+                        live.swap_remove(i.unwrap());
+                    }
+                }
+            }
+            assert_consistent(&queue);
+            assert_eq!(queue.size(), live.len());
+        }
+        assert!(live.iter().all(|&(handle, value)| queue.get(handle) == Some(&value)));
+    }
+
+    #[test]
+    fn test_dijkstra() {
+        // adjacency lists of (neighbour, weight)
+        let graph: Vec<Vec<(usize, u32)>> = vec![
+            vec![(1, 7), (2, 9), (5, 14)],
+            vec![(0, 7), (2, 10), (3, 15)],
+            vec![(0, 9), (1, 10), (3, 11), (5, 2)],
+            vec![(1, 15), (2, 11), (4, 6)],
+            vec![(3, 6), (5, 9)],
+            vec![(0, 14), (2, 2), (4, 9)],
+        ];
+
+        let mut distance = vec![u32::MAX; graph.len()];
+        let mut handles: Vec<Option<Handle>> = vec![None; graph.len()];
+        let mut queue = IndexedPriorityQueue::min();
+        distance[0] = 0;
+        handles[0] = Some(queue.push((0, 0)));
+
+        while let Some((dist, node)) = queue.pop() {
+            for &(next, weight) in &graph[node] {
+                let candidate = dist + weight;
+                if candidate >= distance[next] {
+                    continue;
+                }
+                distance[next] = candidate;
+                match handles[next] {
+                    Some(handle) if queue.contains(handle) => {
+                        queue.change_priority(handle, (candidate, next));
+                    }
+                    _ => handles[next] = Some(queue.push((candidate, next))),
+                }
+            }
+        }
+
+        assert_eq!(distance, vec![0, 7, 9, 20, 20, 11]);
+    }
+}
@@ -3,15 +3,42 @@ mod stack;
 mod doubly_linked_list;
 mod queue;
 mod deque;
-// mod binary_search_tree;
-// mod hash_map;
-// mod priority_queue;
+mod compare;
+pub mod binary_search_tree;
+pub mod avl_tree;
+pub mod tree_map;
+pub mod hash_map;
+pub mod hasher;
+pub mod hash_set;
+pub mod linked_hash_map;
+mod lru_cache;
+mod cuckoo_map;
+pub mod priority_queue;
+mod handle;
+mod indexed_priority_queue;
+mod pairing_heap;
+mod binomial_heap;
+mod fibonacci_heap;
+#[cfg(test)]
+mod heap_tests;
 
 pub use singly_linked_list::SinglyLinkedList;
 pub use stack::Stack;
 pub use doubly_linked_list::DoublyLinkedList;
 pub use queue::Queue;
 pub use deque::Deque;
-// pub use binary_search_tree::BinaryTree;
-// pub use hash_map::HashMap;
-// pub use priority_queue::PriorityQueue;
\ No newline at end of file
+pub use compare::{ByKey, Compare, Natural, Reversed};
+pub use binary_search_tree::BinarySearchTree;
+pub use avl_tree::AvlTree;
+pub use tree_map::TreeMap;
+pub use hash_map::HashMap;
+pub use hash_set::HashSet;
+pub use linked_hash_map::LinkedHashMap;
+pub use lru_cache::LruCache;
+pub use cuckoo_map::CuckooMap;
+pub use priority_queue::PriorityQueue;
+pub use handle::{Handle, NodeHandle};
+pub use indexed_priority_queue::IndexedPriorityQueue;
+pub use pairing_heap::PairingHeap;
+pub use binomial_heap::BinomialHeap;
+pub use fibonacci_heap::FibonacciHeap;
\ No newline at end of file
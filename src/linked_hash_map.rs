@@ -0,0 +1,650 @@
+use std::borrow::Borrow;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::marker::PhantomData;
+use std::ptr;
+
+use crate::hash_map::{Entry, HashMap};
+
+// which end of the order list an entry goes to when it's used
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Order {
+    // entries stay where they were first inserted; overwriting a value doesn't move it
+    #[default]
+    Insertion,
+    // get, get_mut and overwriting insert all move the entry to the back,
+    // so the front is always the least recently used entry
+    Access,
+}
+
+// each entry lives in its own heap node, linked into the order list the same way
+// DoublyLinkedList links its nodes
+struct Node<K, V> {
+    key: K,
+    value: V,
+    prev: *mut Node<K, V>,
+    next: *mut Node<K, V>,
+}
+
+// the hash table's key: a pointer to the key inside a node. nodes never move once allocated,
+// so the pointer stays valid for as long as the entry exists
+struct KeyRef<K> {
+    key: *const K,
+}
+
+impl<K: Hash> Hash for KeyRef<K> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        unsafe { (*self.key).hash(state) }
+    }
+}
+
+impl<K: PartialEq> PartialEq for KeyRef<K> {
+    fn eq(&self, other: &Self) -> bool {
+        unsafe { *self.key == *other.key }
+    }
+}
+
+impl<K: Eq> Eq for KeyRef<K> {}
+
+// lets the table be searched with any borrowed form of the key: a KeyRef<K> borrows as a
+// Lookup<Q> whenever K borrows as Q, and hashes and compares the same way
+#[repr(transparent)]
+struct Lookup<Q: ?Sized>(Q);
+
+impl<Q: ?Sized> Lookup<Q> {
+    fn new(key: &Q) -> &Lookup<Q> {
+        // repr(transparent) guarantees Lookup<Q> has exactly Q's layout
+        unsafe { &*(key as *const Q as *const Lookup<Q>) }
+    }
+}
+
+impl<Q: Hash + ?Sized> Hash for Lookup<Q> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl<Q: PartialEq + ?Sized> PartialEq for Lookup<Q> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<Q: Eq + ?Sized> Eq for Lookup<Q> {}
+
+impl<K: Borrow<Q>, Q: ?Sized> Borrow<Lookup<Q>> for KeyRef<K> {
+    fn borrow(&self) -> &Lookup<Q> {
+        Lookup::new(unsafe { (*self.key).borrow() })
+    }
+}
+
+// a hash map that remembers an order over its entries: the hash table finds a key's node
+// in O(1), and the list through the nodes gives the order, also with O(1) updates
+pub struct LinkedHashMap<K, V, S = RandomState> {
+    map: HashMap<KeyRef<K>, *mut Node<K, V>, S>,
+    head: *mut Node<K, V>, // the oldest entry
+    tail: *mut Node<K, V>, // the newest (or most recently used) entry
+    order: Order,
+}
+
+impl<K, V> LinkedHashMap<K, V, RandomState> {
+    pub fn new() -> Self {
+        Self::with_order(Order::default())
+    }
+
+    pub fn with_order(order: Order) -> Self {
+        Self::with_order_and_hasher(order, RandomState::new())
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut map = Self::new();
+        map.map.reserve(capacity);
+        map
+    }
+}
+
+impl<K, V, S> LinkedHashMap<K, V, S> {
+    pub fn with_hasher(hash_builder: S) -> Self {
+        Self::with_order_and_hasher(Order::default(), hash_builder)
+    }
+
+    pub fn with_order_and_hasher(order: Order, hash_builder: S) -> Self {
+        LinkedHashMap {
+            map: HashMap::with_hasher(hash_builder),
+            head: ptr::null_mut(),
+            tail: ptr::null_mut(),
+            order,
+        }
+    }
+
+    pub fn order(&self) -> Order {
+        self.order
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.map.capacity()
+    }
+
+    pub fn front(&self) -> Option<(&K, &V)> {
+        if self.head.is_null() {
+            return None;
+        }
+
+        unsafe { Some((&(*self.head).key, &(*self.head).value)) }
+    }
+
+    pub fn back(&self) -> Option<(&K, &V)> {
+        if self.tail.is_null() {
+            return None;
+        }
+
+        unsafe { Some((&(*self.tail).key, &(*self.tail).value)) }
+    }
+
+    // front to back
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            front: self.head,
+            back: self.tail,
+            remaining: self.len(),
+            marker: PhantomData,
+        }
+    }
+
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &K> {
+        self.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &V> {
+        self.iter().map(|(_, value)| value)
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+
+        let mut current = self.head;
+        while !current.is_null() {
+            let node = unsafe { Box::from_raw(current) };
+            current = node.next;
+        }
+        self.head = ptr::null_mut();
+        self.tail = ptr::null_mut();
+    }
+
+    fn link_back(&mut self, node: *mut Node<K, V>) {
+        unsafe {
+            (*node).prev = self.tail;
+            (*node).next = ptr::null_mut();
+
+            if !self.tail.is_null() {
+                (*self.tail).next = node;
+            } else {
+                self.head = node;
+            }
+            self.tail = node;
+        }
+    }
+
+    fn unlink(&mut self, node: *mut Node<K, V>) {
+        unsafe {
+            let prev = (*node).prev;
+            let next = (*node).next;
+
+            if !prev.is_null() {
+                (*prev).next = next;
+            } else {
+                self.head = next;
+            }
+
+            if !next.is_null() {
+                (*next).prev = prev;
+            } else {
+                self.tail = prev;
+            }
+        }
+    }
+
+    // records a use of `node` under access order
+    fn touch(&mut self, node: *mut Node<K, V>) {
+        if self.order == Order::Access && node != self.tail {
+            self.unlink(node);
+            self.link_back(node);
+        }
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher> LinkedHashMap<K, V, S> {
+    // a new key goes to the back; an existing key keeps its place (under insertion order)
+    // and the old value is returned
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        // the probe only needs a pointer to `key` where it is now; a vacant entry then stores
+        // a pointer to the key's new home in the node, so the table is searched just once
+        let node = match self.map.entry(KeyRef { key: &key }) {
+            Entry::Occupied(entry) => *entry.get(),
+            Entry::Vacant(entry) => {
+                let node = Box::into_raw(Box::new(Node {
+                    key,
+                    value,
+                    prev: ptr::null_mut(),
+                    next: ptr::null_mut(),
+                }));
+                entry.insert_with_key(KeyRef { key: unsafe { &(*node).key } }, node);
+                self.link_back(node);
+                return None;
+            }
+        };
+
+        self.touch(node);
+        Some(unsafe { std::mem::replace(&mut (*node).value, value) })
+    }
+
+    // under access order this counts as a use and moves the entry to the back
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let node = *self.map.get(Lookup::new(key))?;
+        self.touch(node);
+        unsafe { Some(&(*node).value) }
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let node = *self.map.get(Lookup::new(key))?;
+        self.touch(node);
+        unsafe { Some(&mut (*node).value) }
+    }
+
+    // looks a value up without counting as a use, whatever the order
+    pub fn peek<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let node = *self.map.get(Lookup::new(key))?;
+        unsafe { Some(&(*node).value) }
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.contains_key(Lookup::new(key))
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.remove_entry(key).map(|(_, value)| value)
+    }
+
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let node = self.map.remove(Lookup::new(key))?;
+        Some(self.free(node))
+    }
+
+    // returns false if the key isn't present
+    pub fn move_to_back<Q>(&mut self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let Some(&node) = self.map.get(Lookup::new(key)) else {
+            return false;
+        };
+        if node != self.tail {
+            self.unlink(node);
+            self.link_back(node);
+        }
+        true
+    }
+
+    pub fn pop_front(&mut self) -> Option<(K, V)> {
+        if self.head.is_null() {
+            return None;
+        }
+
+        let node = self.head;
+        self.map.remove(unsafe { Lookup::new(&(*node).key) });
+        Some(self.free(node))
+    }
+
+    pub fn pop_back(&mut self) -> Option<(K, V)> {
+        if self.tail.is_null() {
+            return None;
+        }
+
+        let node = self.tail;
+        self.map.remove(unsafe { Lookup::new(&(*node).key) });
+        Some(self.free(node))
+    }
+
+    // unlinks a node that has already been taken out of the table and frees it
+    fn free(&mut self, node: *mut Node<K, V>) -> (K, V) {
+        self.unlink(node);
+        let node = unsafe { Box::from_raw(node) };
+        (node.key, node.value)
+    }
+}
+
+impl<K, V, S: Default> Default for LinkedHashMap<K, V, S> {
+    fn default() -> Self {
+        Self::with_order_and_hasher(Order::default(), S::default())
+    }
+}
+
+impl<K, V, S> Drop for LinkedHashMap<K, V, S> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+pub struct Iter<'a, K, V> {
+    front: *const Node<K, V>,
+    back: *const Node<K, V>,
+    remaining: usize,
+    marker: PhantomData<&'a Node<K, V>>,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        self.remaining -= 1;
+        unsafe {
+            let node = &*self.front;
+            self.front = node.next;
+            Some((&node.key, &node.value))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V> DoubleEndedIterator for Iter<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        self.remaining -= 1;
+        unsafe {
+            let node = &*self.back;
+            self.back = node.prev;
+            Some((&node.key, &node.value))
+        }
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+impl<'a, K, V, S> IntoIterator for &'a LinkedHashMap<K, V, S> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+// drains the map front to back
+pub struct IntoIter<K, V, S> {
+    map: LinkedHashMap<K, V, S>,
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher> Iterator for IntoIter<K, V, S> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<(K, V)> {
+        self.map.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.map.len(), Some(self.map.len()))
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher> DoubleEndedIterator for IntoIter<K, V, S> {
+    fn next_back(&mut self) -> Option<(K, V)> {
+        self.map.pop_back()
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher> IntoIterator for LinkedHashMap<K, V, S> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V, S>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter { map: self }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    fn keys<K: Clone, V, S>(map: &LinkedHashMap<K, V, S>) -> Vec<K> {
+        map.iter().map(|(key, _)| key.clone()).collect()
+    }
+
+    #[test]
+    fn test_new_map_is_empty() {
+        let map: LinkedHashMap<i32, i32> = LinkedHashMap::new();
+        assert!(map.is_empty());
+        assert_eq!(map.len(), 0);
+        assert_eq!(map.front(), None);
+        assert_eq!(map.back(), None);
+        assert_eq!(map.iter().next(), None);
+        assert_eq!(map.order(), Order::Insertion);
+    }
+
+    #[test]
+    fn test_iterates_in_insertion_order() {
+        let mut map = LinkedHashMap::new();
+        for key in [5, 1, 4, 2, 3] {
+            assert_eq!(map.insert(key, key * 10), None);
+        }
+
+        assert_eq!(keys(&map), vec![5, 1, 4, 2, 3]);
+        assert_eq!(map.values().copied().collect::<Vec<_>>(), vec![50, 10, 40, 20, 30]);
+        assert_eq!(map.iter().rev().map(|(&key, _)| key).collect::<Vec<_>>(), vec![3, 2, 4, 1, 5]);
+        assert_eq!(map.front(), Some((&5, &50)));
+        assert_eq!(map.back(), Some((&3, &30)));
+    }
+
+    #[test]
+    fn test_overwriting_keeps_position() {
+        let mut map = LinkedHashMap::new();
+        map.insert("a", 1);
+        map.insert("b", 2);
+        map.insert("c", 3);
+
+        assert_eq!(map.insert("a", 10), Some(1));
+        assert_eq!(keys(&map), vec!["a", "b", "c"]);
+        assert_eq!(map.get("a"), Some(&10));
+        assert_eq!(map.len(), 3);
+    }
+
+    #[test]
+    fn test_get_peek_and_get_mut() {
+        let mut map = LinkedHashMap::new();
+        map.insert(String::from("x"), 1);
+
+        assert_eq!(map.get("x"), Some(&1));
+        assert_eq!(map.peek("x"), Some(&1));
+        *map.get_mut("x").unwrap() += 1;
+        assert_eq!(map.peek("x"), Some(&2));
+
+        assert_eq!(map.get("y"), None);
+        assert_eq!(map.peek("y"), None);
+        assert!(map.contains_key("x"));
+        assert!(!map.contains_key("y"));
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut map = LinkedHashMap::new();
+        for key in 0..5 {
+            map.insert(key, key);
+        }
+
+        assert_eq!(map.remove(&2), Some(2));
+        assert_eq!(map.remove(&2), None);
+        assert_eq!(map.remove_entry(&0), Some((0, 0)));
+        assert_eq!(map.remove(&4), Some(4));
+
+        assert_eq!(keys(&map), vec![1, 3]);
+        assert_eq!(map.front(), Some((&1, &1)));
+        assert_eq!(map.back(), Some((&3, &3)));
+        assert_eq!(map.len(), 2);
+
+        map.insert(0, 0);
+        assert_eq!(keys(&map), vec![1, 3, 0]);
+    }
+
+    #[test]
+    fn test_pop_front_and_back() {
+        let mut map = LinkedHashMap::new();
+        for key in 0..4 {
+            map.insert(key, key * 2);
+        }
+
+        assert_eq!(map.pop_front(), Some((0, 0)));
+        assert_eq!(map.pop_back(), Some((3, 6)));
+        assert_eq!(map.pop_front(), Some((1, 2)));
+        assert_eq!(map.pop_back(), Some((2, 4)));
+        assert_eq!(map.pop_front(), None);
+        assert_eq!(map.pop_back(), None);
+        assert!(map.is_empty());
+        assert!(!map.contains_key(&0));
+    }
+
+    #[test]
+    fn test_move_to_back() {
+        let mut map = LinkedHashMap::new();
+        for key in 0..4 {
+            map.insert(key, ());
+        }
+
+        assert!(map.move_to_back(&0));
+        assert_eq!(keys(&map), vec![1, 2, 3, 0]);
+        assert!(map.move_to_back(&2));
+        assert_eq!(keys(&map), vec![1, 3, 0, 2]);
+        assert!(map.move_to_back(&2));
+        assert_eq!(keys(&map), vec![1, 3, 0, 2]);
+        assert!(!map.move_to_back(&9));
+        assert_eq!(map.iter().rev().map(|(&key, _)| key).collect::<Vec<_>>(), vec![2, 0, 3, 1]);
+    }
+
+    #[test]
+    fn test_access_order() {
+        let mut map = LinkedHashMap::with_order(Order::Access);
+        for key in 0..5 {
+            map.insert(key, key);
+        }
+
+        map.get(&1);
+        assert_eq!(keys(&map), vec![0, 2, 3, 4, 1]);
+        *map.get_mut(&0).unwrap() += 10;
+        assert_eq!(keys(&map), vec![2, 3, 4, 1, 0]);
+        map.insert(3, 30);
+        assert_eq!(keys(&map), vec![2, 4, 1, 0, 3]);
+
+        // peeking and membership checks aren't accesses
+        map.peek(&2);
+        assert!(map.contains_key(&4));
+        assert_eq!(keys(&map), vec![2, 4, 1, 0, 3]);
+
+        // the front is the least recently used entry
+        assert_eq!(map.pop_front(), Some((2, 2)));
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut map = LinkedHashMap::new();
+        for key in 0..100 {
+            map.insert(key, key);
+        }
+
+        map.clear();
+        assert!(map.is_empty());
+        assert_eq!(map.front(), None);
+        assert_eq!(map.iter().count(), 0);
+        assert!(!map.contains_key(&5));
+
+        map.insert(7, 7);
+        assert_eq!(keys(&map), vec![7]);
+    }
+
+    #[test]
+    fn test_into_iter() {
+        let mut map = LinkedHashMap::new();
+        for key in ["one", "two", "three"] {
+            map.insert(String::from(key), key.len());
+        }
+
+        let mut iter = map.into_iter();
+        assert_eq!(iter.next(), Some((String::from("one"), 3)));
+        assert_eq!(iter.next_back(), Some((String::from("three"), 5)));
+        assert_eq!(iter.collect::<Vec<_>>(), vec![(String::from("two"), 3)]);
+    }
+
+    #[test]
+    fn test_drops_every_entry() {
+        let shared = Rc::new(());
+        let mut map = LinkedHashMap::new();
+        for key in 0..50 {
+            map.insert(key, Rc::clone(&shared));
+        }
+        map.insert(0, Rc::clone(&shared));
+        map.remove(&1);
+        map.pop_back();
+        assert_eq!(Rc::strong_count(&shared), 49);
+
+        drop(map);
+        assert_eq!(Rc::strong_count(&shared), 1);
+    }
+
+    #[test]
+    fn test_survives_table_growth() {
+        // the table moves its entries around as it grows; the nodes and the keys the table
+        // points at must not
+        let mut map = LinkedHashMap::new();
+        for key in 0..10_000 {
+            map.insert(key.to_string(), key);
+        }
+        for key in (0..10_000).step_by(2) {
+            map.remove(key.to_string().as_str());
+        }
+
+        assert_eq!(map.len(), 5_000);
+        assert_eq!(map.peek("9999"), Some(&9_999));
+        assert!(map.values().copied().eq((1..10_000).step_by(2)));
+    }
+}
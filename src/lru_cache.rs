@@ -0,0 +1,317 @@
+use std::borrow::Borrow;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash};
+
+use crate::linked_hash_map::{self, LinkedHashMap, Order};
+
+type EvictionCallback<K, V> = Box<dyn FnMut(&K, &V)>;
+
+// a cache holding at most `capacity` entries. it's an access-ordered LinkedHashMap, so the
+// least recently used entry is always at the front and eviction is O(1)
+pub struct LruCache<K, V, S = RandomState> {
+    map: LinkedHashMap<K, V, S>,
+    capacity: usize,
+    on_evict: Option<EvictionCallback<K, V>>,
+}
+
+impl<K, V> LruCache<K, V, RandomState> {
+    // panics if capacity is 0
+    pub fn new(capacity: usize) -> Self {
+        Self::with_hasher(capacity, RandomState::new())
+    }
+}
+
+impl<K, V, S> LruCache<K, V, S> {
+    pub fn with_hasher(capacity: usize, hash_builder: S) -> Self {
+        assert!(capacity > 0, "an LRU cache needs room for at least one entry");
+        LruCache {
+            map: LinkedHashMap::with_order_and_hasher(Order::Access, hash_builder),
+            capacity,
+            on_evict: None,
+        }
+    }
+
+    // called with every entry the cache drops to make room, whether from `put` or `resize`.
+    // entries taken out explicitly (remove, pop_lru, clear) aren't evictions
+    pub fn set_on_evict(&mut self, callback: impl FnMut(&K, &V) + 'static) {
+        self.on_evict = Some(Box::new(callback));
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    // least recently used first
+    pub fn iter(&self) -> linked_hash_map::Iter<'_, K, V> {
+        self.map.iter()
+    }
+
+    pub fn peek_lru(&self) -> Option<(&K, &V)> {
+        self.map.front()
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher> LruCache<K, V, S> {
+    // marks the entry as the most recently used
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.get(key)
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.get_mut(key)
+    }
+
+    // leaves the recency order alone
+    pub fn peek<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.peek(key)
+    }
+
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.contains_key(key)
+    }
+
+    // inserts or overwrites the entry and makes it the most recently used. if a new key
+    // pushes the cache over capacity, the least recently used entry is evicted and returned
+    pub fn put(&mut self, key: K, value: V) -> Option<(K, V)> {
+        // one probe either way: an existing key is overwritten and promoted by the insert
+        // itself, and a new one lands at the back, so the front is still the one to evict
+        if self.map.insert(key, value).is_some() || self.map.len() <= self.capacity {
+            return None;
+        }
+        self.evict()
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.remove(key)
+    }
+
+    pub fn pop_lru(&mut self) -> Option<(K, V)> {
+        self.map.pop_front()
+    }
+
+    // shrinking evicts least recently used entries until the cache fits. panics if capacity is 0
+    pub fn resize(&mut self, capacity: usize) {
+        assert!(capacity > 0, "an LRU cache needs room for at least one entry");
+        self.capacity = capacity;
+        while self.map.len() > capacity {
+            self.evict();
+        }
+    }
+
+    fn evict(&mut self) -> Option<(K, V)> {
+        let (key, value) = self.map.pop_front()?;
+        if let Some(callback) = &mut self.on_evict {
+            callback(&key, &value);
+        }
+        Some((key, value))
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a LruCache<K, V, S> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = linked_hash_map::Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn keys<K: Clone, V, S>(cache: &LruCache<K, V, S>) -> Vec<K> {
+        cache.iter().map(|(key, _)| key.clone()).collect()
+    }
+
+    #[test]
+    fn test_put_evicts_least_recently_used() {
+        let mut cache = LruCache::new(3);
+        assert_eq!(cache.put(1, "one"), None);
+        assert_eq!(cache.put(2, "two"), None);
+        assert_eq!(cache.put(3, "three"), None);
+        assert_eq!(cache.len(), 3);
+
+        assert_eq!(cache.put(4, "four"), Some((1, "one")));
+        assert_eq!(cache.put(5, "five"), Some((2, "two")));
+        assert_eq!(keys(&cache), vec![3, 4, 5]);
+        assert_eq!(cache.len(), cache.capacity());
+    }
+
+    #[test]
+    fn test_get_promotes() {
+        let mut cache = LruCache::new(3);
+        for key in 1..=3 {
+            cache.put(key, key * 10);
+        }
+
+        assert_eq!(cache.get(&1), Some(&10));
+        assert_eq!(cache.peek_lru(), Some((&2, &20)));
+        assert_eq!(cache.put(4, 40), Some((2, 20)));
+
+        *cache.get_mut(&3).unwrap() += 1;
+        assert_eq!(cache.put(5, 50), Some((1, 10)));
+        assert_eq!(keys(&cache), vec![4, 3, 5]);
+        assert_eq!(cache.get(&9), None);
+    }
+
+    #[test]
+    fn test_peek_does_not_promote() {
+        let mut cache = LruCache::new(2);
+        cache.put("a", 1);
+        cache.put("b", 2);
+
+        assert_eq!(cache.peek("a"), Some(&1));
+        assert!(cache.contains("a"));
+        assert_eq!(cache.put("c", 3), Some(("a", 1)));
+        assert_eq!(cache.peek("a"), None);
+    }
+
+    #[test]
+    fn test_put_existing_key_overwrites_without_evicting() {
+        let mut cache = LruCache::new(2);
+        cache.put(1, 1);
+        cache.put(2, 2);
+
+        assert_eq!(cache.put(1, 100), None);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.peek(&1), Some(&100));
+        // overwriting counts as a use
+        assert_eq!(cache.put(3, 3), Some((2, 2)));
+    }
+
+    #[test]
+    fn test_remove_and_pop_lru() {
+        let mut cache = LruCache::new(4);
+        for key in 0..4 {
+            cache.put(key, key);
+        }
+
+        assert_eq!(cache.remove(&2), Some(2));
+        assert_eq!(cache.remove(&2), None);
+        assert_eq!(cache.pop_lru(), Some((0, 0)));
+        assert_eq!(cache.pop_lru(), Some((1, 1)));
+        assert_eq!(cache.pop_lru(), Some((3, 3)));
+        assert_eq!(cache.pop_lru(), None);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_resize() {
+        let mut cache = LruCache::new(5);
+        for key in 0..5 {
+            cache.put(key, ());
+        }
+        cache.get(&0);
+
+        cache.resize(2);
+        assert_eq!(cache.capacity(), 2);
+        assert_eq!(keys(&cache), vec![4, 0]);
+
+        cache.resize(4);
+        cache.put(7, ());
+        cache.put(8, ());
+        assert_eq!(cache.len(), 4);
+        assert_eq!(cache.put(9, ()), Some((4, ())));
+    }
+
+    #[test]
+    fn test_eviction_callback() {
+        let evicted = Rc::new(RefCell::new(Vec::new()));
+        let mut cache = LruCache::new(2);
+        let log = Rc::clone(&evicted);
+        cache.set_on_evict(move |&key, &value| log.borrow_mut().push((key, value)));
+
+        cache.put(1, 'a');
+        cache.put(2, 'b');
+        cache.put(3, 'c');
+        cache.put(3, 'C');
+        assert_eq!(*RefCell::borrow(&evicted), vec![(1, 'a')]);
+
+        // explicit removals aren't evictions
+        cache.remove(&2);
+        cache.pop_lru();
+        assert_eq!(*RefCell::borrow(&evicted), vec![(1, 'a')]);
+
+        for key in 4..8 {
+            cache.put(key, 'x');
+        }
+        cache.resize(1);
+        assert_eq!(*RefCell::borrow(&evicted), vec![(1, 'a'), (4, 'x'), (5, 'x'), (6, 'x')]);
+        assert_eq!(keys(&cache), vec![7]);
+    }
+
+    #[test]
+    fn test_matches_a_naive_model() {
+        // a Vec kept in recency order, least recent first
+        let mut model: Vec<(u32, u32)> = Vec::new();
+        let mut cache = LruCache::new(8);
+        let mut state = 0x2545_f491u32;
+        for step in 0..5_000 {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            let key = state % 20;
+
+            if state.is_multiple_of(3) {
+                let expected = model.iter().position(|&(k, _)| k == key).map(|i| model.remove(i));
+                if let Some(entry) = expected {
+                    model.push(entry);
+                }
+                assert_eq!(cache.get(&key), expected.map(|(_, v)| v).as_ref());
+            } else {
+                let evicted = match model.iter().position(|&(k, _)| k == key) {
+                    Some(i) => {
+                        model.remove(i);
+                        None
+                    }
+                    None if model.len() == 8 => Some(model.remove(0)),
+                    None => None,
+                };
+                model.push((key, step));
+                assert_eq!(cache.put(key, step), evicted);
+            }
+            assert!(cache.iter().map(|(&k, &v)| (k, v)).eq(model.iter().copied()));
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_zero_capacity_panics() {
+        LruCache::<i32, i32>::new(0);
+    }
+}
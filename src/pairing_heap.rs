@@ -0,0 +1,336 @@
+use std::cmp::Ordering;
+use std::ptr;
+
+use crate::compare::{ByKey, Compare, Natural, Reversed};
+use crate::handle::{NodeHandle, Pool};
+
+// a heap-ordered multiway tree: each node's children hang off it as a doubly linked sibling
+// list, the leftmost child pointing back up at the parent
+struct Node<T> {
+    value: T,
+    child: *mut Node<T>, // leftmost child
+    next: *mut Node<T>,  // right sibling
+    prev: *mut Node<T>,  // left sibling, or the parent for a leftmost child
+}
+
+// a heap that melds in O(1): merging is just making the lesser root, under the comparator,
+// a child of the greater. push and decrease_key are O(1) too, while pop restructures the
+// root's children in two pairing passes, amortized O(log n). like PriorityQueue it's a
+// max-heap with the natural order and a min-heap with min()
+pub struct PairingHeap<T, C = Natural> {
+    root: *mut Node<T>,
+    size: usize,
+    pool: Pool<Node<T>>,
+    cmp: C,
+}
+
+impl<T: Ord> PairingHeap<T> {
+    pub fn new() -> Self {
+        Self::with_comparator(Natural)
+    }
+
+    // the same as new(), for symmetry with min()
+    pub fn max() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord> PairingHeap<T, Reversed> {
+    // pops the smallest item first, which makes decrease_key a literal decrease
+    pub fn min() -> Self {
+        Self::with_comparator(Reversed(Natural))
+    }
+}
+
+impl<T, F> PairingHeap<T, ByKey<F>> {
+    pub fn with_key<K: Ord>(key: F) -> Self
+    where
+        F: Fn(&T) -> K,
+    {
+        Self::with_comparator(ByKey(key))
+    }
+}
+
+impl<T, F> PairingHeap<T, Reversed<ByKey<F>>> {
+    pub fn min_by_key<K: Ord>(key: F) -> Self
+    where
+        F: Fn(&T) -> K,
+    {
+        Self::with_comparator(Reversed(ByKey(key)))
+    }
+}
+
+impl<T, C> PairingHeap<T, C> {
+    pub fn with_comparator(cmp: C) -> Self {
+        PairingHeap {
+            root: ptr::null_mut(),
+            size: 0,
+            pool: Pool::new(),
+            cmp,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    pub fn peek(&self) -> Option<&T> {
+        if self.root.is_null() {
+            return None;
+        }
+
+        unsafe { Some(&(*self.root).value) }
+    }
+
+    pub fn get(&self, handle: &NodeHandle) -> Option<&T> {
+        let node = self.pool.resolve(handle)?;
+        unsafe { Some(&(*node).value) }
+    }
+
+    // false once the item has been popped or removed, or if it's in another heap
+    pub fn contains(&self, handle: &NodeHandle) -> bool {
+        self.pool.resolve(handle).is_some()
+    }
+}
+
+impl<T, C: Compare<T>> PairingHeap<T, C> {
+    pub fn push(&mut self, value: T) -> NodeHandle {
+        let node = self.pool.alloc(Node {
+            value,
+            child: ptr::null_mut(),
+            next: ptr::null_mut(),
+            prev: ptr::null_mut(),
+        });
+        self.root = self.meld(self.root, node);
+        self.size += 1;
+        self.pool.handle(node)
+    }
+
+    // removes and returns the greatest item under the comparator
+    pub fn pop(&mut self) -> Option<T> {
+        if self.root.is_null() {
+            return None;
+        }
+
+        let root = self.root;
+        self.root = self.pair_up(unsafe { (*root).child });
+        self.size -= 1;
+        Some(self.pool.free(root).value)
+    }
+
+    // takes every item of `other`. handles into `other` carry on working in this heap
+    pub fn merge(&mut self, mut other: PairingHeap<T, C>) {
+        self.pool.absorb(&mut other.pool);
+        self.root = self.meld(self.root, other.root);
+        self.size += other.size;
+    }
+
+    // moves the item towards the top by replacing its value, and returns the old one, or None
+    // for a stale handle. the name is from min-heaps, where that means lowering the value; in
+    // general it panics if the old value is greater than `value` under the comparator
+    pub fn decrease_key(&mut self, handle: &NodeHandle, value: T) -> Option<T> {
+        let node = self.pool.resolve(handle)?;
+        unsafe {
+            assert!(!self.greater(&(*node).value, &value), "decrease_key can't move an item away from the top");
+            let old = std::mem::replace(&mut (*node).value, value);
+
+            // the node's subtree is still heap-ordered; only its link to the parent may not be
+            if node != self.root {
+                self.cut(node);
+                self.root = self.meld(self.root, node);
+            }
+            Some(old)
+        }
+    }
+
+    pub fn remove(&mut self, handle: &NodeHandle) -> Option<T> {
+        let node = self.pool.resolve(handle)?;
+        if node == self.root {
+            return self.pop();
+        }
+
+        self.cut(node);
+        let children = self.pair_up(unsafe { (*node).child });
+        self.root = self.meld(self.root, children);
+        self.size -= 1;
+        Some(self.pool.free(node).value)
+    }
+
+    // links two roots (either may be null) and returns the new root
+    fn meld(&mut self, a: *mut Node<T>, b: *mut Node<T>) -> *mut Node<T> {
+        if a.is_null() {
+            return b;
+        }
+        if b.is_null() {
+            return a;
+        }
+
+        unsafe {
+            let (parent, child) = if self.greater(&(*b).value, &(*a).value) { (b, a) } else { (a, b) };
+            (*child).prev = parent;
+            (*child).next = (*parent).child;
+            if !(*parent).child.is_null() {
+                (*(*parent).child).prev = child;
+            }
+            (*parent).child = child;
+            (*parent).next = ptr::null_mut();
+            (*parent).prev = ptr::null_mut();
+            parent
+        }
+    }
+
+    // detaches a non-root node, and its subtree, from its parent
+    fn cut(&mut self, node: *mut Node<T>) {
+        unsafe {
+            let prev = (*node).prev;
+            let next = (*node).next;
+            if (*prev).child == node {
+                (*prev).child = next;
+            } else {
+                (*prev).next = next;
+            }
+            if !next.is_null() {
+                (*next).prev = prev;
+            }
+            (*node).prev = ptr::null_mut();
+            (*node).next = ptr::null_mut();
+        }
+    }
+
+    // melds a sibling list into one tree: first pair up neighbours left to right, then meld
+    // the pairs right to left. this two-pass order is what gives pop its O(log n) bound
+    fn pair_up(&mut self, first: *mut Node<T>) -> *mut Node<T> {
+        let mut pairs = Vec::new();
+        let mut current = first;
+        while !current.is_null() {
+            unsafe {
+                let a = current;
+                let b = (*a).next;
+                current = if b.is_null() { ptr::null_mut() } else { (*b).next };
+                (*a).next = ptr::null_mut();
+                (*a).prev = ptr::null_mut();
+                if !b.is_null() {
+                    (*b).next = ptr::null_mut();
+                    (*b).prev = ptr::null_mut();
+                }
+                pairs.push(self.meld(a, b));
+            }
+        }
+
+        let mut root = ptr::null_mut();
+        while let Some(tree) = pairs.pop() {
+            root = self.meld(tree, root);
+        }
+        root
+    }
+
+    fn greater(&self, a: &T, b: &T) -> bool {
+        self.cmp.compare(a, b) == Ordering::Greater
+    }
+}
+
+impl<T, C: Default> Default for PairingHeap<T, C> {
+    fn default() -> Self {
+        Self::with_comparator(C::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // heap order, and every link agrees with the one pointing the other way
+    fn assert_well_formed<T, C: Compare<T>>(heap: &PairingHeap<T, C>) {
+        fn check<T, C: Compare<T>>(heap: &PairingHeap<T, C>, node: *mut Node<T>) -> usize {
+            let mut count = 1;
+            unsafe {
+                let mut prev = node;
+                let mut child = (*node).child;
+                while !child.is_null() {
+                    assert!((*child).prev == prev);
+                    assert!(!heap.greater(&(*child).value, &(*node).value));
+                    count += check(heap, child);
+                    prev = child;
+                    child = (*child).next;
+                }
+            }
+            count
+        }
+
+        if heap.root.is_null() {
+            assert_eq!(heap.size, 0);
+        } else {
+            unsafe {
+                assert!((*heap.root).prev.is_null() && (*heap.root).next.is_null());
+            }
+            assert_eq!(check(heap, heap.root), heap.size);
+        }
+    }
+
+    #[test]
+    fn test_structure_through_operations() {
+        let mut heap = PairingHeap::min();
+        let handles: Vec<NodeHandle> = (0..200).map(|i| heap.push((i * 37) % 101)).collect();
+        assert_well_formed(&heap);
+
+        for (i, handle) in handles.iter().enumerate().step_by(7) {
+            heap.decrease_key(handle, -(i as i32));
+            assert_well_formed(&heap);
+        }
+        for handle in handles.iter().skip(3).step_by(11) {
+            heap.remove(handle);
+            assert_well_formed(&heap);
+        }
+        for _ in 0..50 {
+            heap.pop();
+            assert_well_formed(&heap);
+        }
+    }
+
+    #[test]
+    fn test_merge_is_a_single_link() {
+        let mut a = PairingHeap::min();
+        let mut b = PairingHeap::min();
+        for i in 0..10 {
+            a.push(i * 2);
+            b.push(i * 2 + 1);
+        }
+        let b_root = b.root;
+
+        a.merge(b);
+        assert_well_formed(&a);
+        // b's root became the leftmost child of a's, untouched otherwise
+        unsafe { assert!((*a.root).child == b_root) };
+        assert_eq!(a.size(), 20);
+    }
+
+    #[test]
+    fn test_comparators() {
+        let mut heap = PairingHeap::new();
+        let handle = heap.push(3);
+        heap.push(5);
+        // with the natural order decrease_key raises the value
+        heap.decrease_key(&handle, 9);
+        assert_eq!(heap.pop(), Some(9));
+        assert_eq!(heap.pop(), Some(5));
+
+        let mut jobs = PairingHeap::min_by_key(|job: &(&str, u32)| job.1);
+        jobs.push(("report", 30));
+        let handle = jobs.push(("backup", 50));
+        jobs.decrease_key(&handle, ("backup", 10));
+        assert_eq!(jobs.pop(), Some(("backup", 10)));
+        assert_eq!(jobs.pop(), Some(("report", 30)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_decrease_key_rejects_increase() {
+        let mut heap = PairingHeap::min();
+        let handle = heap.push(5);
+        heap.decrease_key(&handle, 6);
+    }
+}
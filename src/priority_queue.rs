@@ -0,0 +1,410 @@
+use std::cmp::Ordering;
+use std::ops::{Deref, DerefMut};
+
+use crate::compare::{ByKey, Compare, Natural, Reversed};
+
+// a binary heap stored in a Vec: the children of index i are at 2i + 1 and 2i + 2, and no
+// child is greater than its parent under the comparator, so the greatest item is always at
+// index 0. with the natural order that's a max-heap; Reversed turns it into a min-heap
+pub struct PriorityQueue<T, C = Natural> {
+    data: Vec<T>,
+    cmp: C,
+}
+
+impl<T: Ord> PriorityQueue<T> {
+    pub fn new() -> Self {
+        Self::with_comparator(Natural)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut queue = Self::new();
+        queue.data.reserve(capacity);
+        queue
+    }
+
+    // the same as new(), for symmetry with min()
+    pub fn max() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord> PriorityQueue<T, Reversed> {
+    // pops the smallest item first
+    pub fn min() -> Self {
+        Self::with_comparator(Reversed(Natural))
+    }
+}
+
+impl<T, F> PriorityQueue<T, ByKey<F>> {
+    // pops the item with the largest key first, like new(); the key function is called on
+    // every comparison, so it should be cheap
+    pub fn with_key<K: Ord>(key: F) -> Self
+    where
+        F: Fn(&T) -> K,
+    {
+        Self::with_comparator(ByKey(key))
+    }
+}
+
+impl<T, F> PriorityQueue<T, Reversed<ByKey<F>>> {
+    // pops the item with the smallest key first, e.g. the job with the earliest deadline
+    pub fn min_by_key<K: Ord>(key: F) -> Self
+    where
+        F: Fn(&T) -> K,
+    {
+        Self::with_comparator(Reversed(ByKey(key)))
+    }
+}
+
+impl<T, C> PriorityQueue<T, C> {
+    pub fn with_comparator(cmp: C) -> Self {
+        PriorityQueue { data: Vec::new(), cmp }
+    }
+
+    pub fn peek(&self) -> Option<&T> {
+        self.data.first()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn size(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.data.clear();
+    }
+
+    // the items in heap order, which is no particular order
+    pub fn into_vec(self) -> Vec<T> {
+        self.data
+    }
+}
+
+impl<T, C: Compare<T>> PriorityQueue<T, C> {
+    // heapifies `data` in O(n)
+    pub fn from_vec_by(data: Vec<T>, cmp: C) -> Self {
+        let mut queue = PriorityQueue { data, cmp };
+        queue.heapify();
+        queue
+    }
+
+    pub fn push(&mut self, value: T) {
+        self.data.push(value);
+        self.sift_up(self.data.len() - 1);
+    }
+
+    // removes and returns the greatest item under the comparator
+    pub fn pop(&mut self) -> Option<T> {
+        let last = self.data.pop()?;
+        if self.data.is_empty() {
+            return Some(last);
+        }
+
+        let top = std::mem::replace(&mut self.data[0], last);
+        self.sift_down(0, self.data.len());
+        Some(top)
+    }
+
+    // mutable access to the greatest item. the heap is repaired when the guard is dropped,
+    // so the item can be changed freely in the meantime
+    pub fn peek_mut(&mut self) -> Option<PeekMut<'_, T, C>> {
+        if self.data.is_empty() {
+            return None;
+        }
+
+        Some(PeekMut { queue: self })
+    }
+
+    // ascending order under the comparator, in O(n log n) and without allocating
+    pub fn into_sorted_vec(mut self) -> Vec<T> {
+        // repeatedly swap the greatest remaining item to the end of the shrinking heap
+        let mut end = self.data.len();
+        while end > 1 {
+            end -= 1;
+            self.data.swap(0, end);
+            self.sift_down(0, end);
+        }
+        self.data
+    }
+
+    fn sift_up(&mut self, mut index: usize) {
+        while index > 0 {
+            let parent = (index - 1) / 2;
+            if !self.greater(index, parent) {
+                break;
+            }
+            self.data.swap(index, parent);
+            index = parent;
+        }
+    }
+
+    // only the first `end` items are treated as part of the heap
+    fn sift_down(&mut self, mut index: usize, end: usize) {
+        loop {
+            let left = 2 * index + 1;
+            if left >= end {
+                break;
+            }
+
+            let right = left + 1;
+            let larger = if right < end && self.greater(right, left) { right } else { left };
+            if !self.greater(larger, index) {
+                break;
+            }
+            self.data.swap(index, larger);
+            index = larger;
+        }
+    }
+
+    fn greater(&self, a: usize, b: usize) -> bool {
+        self.cmp.compare(&self.data[a], &self.data[b]) == Ordering::Greater
+    }
+
+    // Floyd's bottom-up construction: sifting down every parent from the last one up is O(n),
+    // since most nodes sit near the bottom and only move a level or two
+    fn heapify(&mut self) {
+        let len = self.data.len();
+        for index in (0..len / 2).rev() {
+            self.sift_down(index, len);
+        }
+    }
+}
+
+impl<T, C: Default> Default for PriorityQueue<T, C> {
+    fn default() -> Self {
+        Self::with_comparator(C::default())
+    }
+}
+
+impl<T: Ord> From<Vec<T>> for PriorityQueue<T> {
+    fn from(data: Vec<T>) -> Self {
+        Self::from_vec_by(data, Natural)
+    }
+}
+
+impl<T: Ord> FromIterator<T> for PriorityQueue<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        PriorityQueue::from(iter.into_iter().collect::<Vec<T>>())
+    }
+}
+
+pub struct PeekMut<'a, T, C: Compare<T> = Natural> {
+    queue: &'a mut PriorityQueue<T, C>,
+}
+
+impl<T, C: Compare<T>> PeekMut<'_, T, C> {
+    // takes the peeked item out of the queue
+    pub fn pop(this: PeekMut<'_, T, C>) -> T {
+        let value = this.queue.pop().unwrap();
+        // the heap is already in order; skip the sift in Drop
+        std::mem::forget(this);
+        value
+    }
+}
+
+impl<T, C: Compare<T>> Deref for PeekMut<'_, T, C> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.queue.data[0]
+    }
+}
+
+impl<T, C: Compare<T>> DerefMut for PeekMut<'_, T, C> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.queue.data[0]
+    }
+}
+
+impl<T, C: Compare<T>> Drop for PeekMut<'_, T, C> {
+    fn drop(&mut self) {
+        let len = self.queue.data.len();
+        self.queue.sift_down(0, len);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_heap<T, C: Compare<T>>(queue: &PriorityQueue<T, C>) {
+        for index in 1..queue.data.len() {
+            assert!(!queue.greater(index, (index - 1) / 2));
+        }
+    }
+
+    fn drain<T, C: Compare<T>>(queue: &mut PriorityQueue<T, C>) -> Vec<T> {
+        std::iter::from_fn(|| queue.pop()).collect()
+    }
+
+    #[test]
+    fn test_new_is_empty() {
+        let mut queue: PriorityQueue<i32> = PriorityQueue::new();
+        assert!(queue.is_empty());
+        assert_eq!(queue.size(), 0);
+        assert_eq!(queue.peek(), None);
+        assert!(queue.peek_mut().is_none());
+        assert_eq!(queue.pop(), None);
+    }
+
+    #[test]
+    fn test_push_and_pop_in_priority_order() {
+        let mut queue = PriorityQueue::new();
+        for value in [5, 1, 8, 3, 9, 2, 8] {
+            queue.push(value);
+            assert_heap(&queue);
+        }
+
+        assert_eq!(queue.size(), 7);
+        assert_eq!(queue.peek(), Some(&9));
+        assert_eq!(drain(&mut queue), vec![9, 8, 8, 5, 3, 2, 1]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_peek_mut_restores_order() {
+        let mut queue = PriorityQueue::from(vec![10, 7, 3, 5]);
+
+        *queue.peek_mut().unwrap() = 1;
+        assert_heap(&queue);
+        assert_eq!(queue.peek(), Some(&7));
+
+        // raising the top item keeps it on top
+        *queue.peek_mut().unwrap() += 100;
+        assert_eq!(queue.peek(), Some(&107));
+
+        {
+            let mut top = queue.peek_mut().unwrap();
+            *top = 0;
+            // the guard still sees the item it was created for
+            assert_eq!(*top, 0);
+        }
+        assert_eq!(drain(&mut queue), vec![5, 3, 1, 0]);
+    }
+
+    #[test]
+    fn test_peek_mut_pop() {
+        let mut queue = PriorityQueue::from(vec![4, 9, 6]);
+
+        let top = queue.peek_mut().unwrap();
+        assert_eq!(PeekMut::pop(top), 9);
+        assert_eq!(queue.size(), 2);
+        assert_heap(&queue);
+        assert_eq!(queue.pop(), Some(6));
+    }
+
+    #[test]
+    fn test_from_vec_heapifies() {
+        let mut state = 0x1234_5678u32;
+        let values: Vec<u32> = (0..1_000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state % 500
+            })
+            .collect();
+
+        let mut queue = PriorityQueue::from(values.clone());
+        assert_heap(&queue);
+        assert_eq!(queue.size(), values.len());
+
+        let mut expected = values;
+        expected.sort_unstable_by(|a, b| b.cmp(a));
+        assert_eq!(drain(&mut queue), expected);
+
+        let empty: PriorityQueue<u32> = PriorityQueue::from(Vec::new());
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_into_sorted_vec() {
+        let queue: PriorityQueue<_> = [3, 1, 4, 1, 5, 9, 2, 6].into_iter().collect();
+        assert_eq!(queue.into_sorted_vec(), vec![1, 1, 2, 3, 4, 5, 6, 9]);
+
+        let single = PriorityQueue::from(vec![42]);
+        assert_eq!(single.into_sorted_vec(), vec![42]);
+    }
+
+    #[test]
+    fn test_into_vec_keeps_every_item() {
+        let queue = PriorityQueue::from(vec![2, 7, 1, 8]);
+        let mut items = queue.into_vec();
+        items.sort_unstable();
+        assert_eq!(items, vec![1, 2, 7, 8]);
+    }
+
+    #[test]
+    fn test_interleaved_operations() {
+        let mut queue = PriorityQueue::new();
+        queue.push(3);
+        queue.push(10);
+        assert_eq!(queue.pop(), Some(10));
+        queue.push(7);
+        queue.push(1);
+        assert_eq!(queue.pop(), Some(7));
+        assert_eq!(queue.peek(), Some(&3));
+
+        queue.clear();
+        assert!(queue.is_empty());
+        queue.push(4);
+        assert_eq!(queue.pop(), Some(4));
+    }
+
+    #[test]
+    fn test_min_and_max() {
+        let mut min = PriorityQueue::min();
+        let mut max = PriorityQueue::max();
+        for value in [5, 1, 8, 3, 9, 2] {
+            min.push(value);
+            max.push(value);
+        }
+
+        assert_eq!(min.peek(), Some(&1));
+        assert_eq!(max.peek(), Some(&9));
+        assert_eq!(drain(&mut min), vec![1, 2, 3, 5, 8, 9]);
+        assert_eq!(drain(&mut max), vec![9, 8, 5, 3, 2, 1]);
+
+        // into_sorted_vec follows the comparator, so a min-heap sorts descending
+        let min = PriorityQueue::from_vec_by(vec![2, 7, 1], Reversed(Natural));
+        assert_eq!(min.into_sorted_vec(), vec![7, 2, 1]);
+    }
+
+    #[test]
+    fn test_with_key() {
+        #[derive(Debug, PartialEq)]
+        struct Job {
+            name: &'static str,
+            deadline: u32,
+        }
+
+        let jobs = [("report", 30), ("deploy", 10), ("review", 20)];
+        let mut by_deadline = PriorityQueue::min_by_key(|job: &Job| job.deadline);
+        let mut latest_first = PriorityQueue::with_key(|job: &Job| job.deadline);
+        for (name, deadline) in jobs {
+            by_deadline.push(Job { name, deadline });
+            latest_first.push(Job { name, deadline });
+        }
+
+        let order: Vec<_> = drain(&mut by_deadline).into_iter().map(|job| job.name).collect();
+        assert_eq!(order, vec!["deploy", "review", "report"]);
+        assert_eq!(latest_first.pop().map(|job| job.name), Some("report"));
+
+        // peek_mut repairs the heap under the key order too
+        latest_first.peek_mut().unwrap().deadline = 0;
+        assert_heap(&latest_first);
+        assert_eq!(latest_first.peek().map(|job| job.name), Some("deploy"));
+    }
+
+    #[test]
+    fn test_closure_comparator() {
+        let mut queue = PriorityQueue::with_comparator(|a: &&str, b: &&str| a.len().cmp(&b.len()));
+        for word in ["a", "abcd", "ab", "abc"] {
+            queue.push(word);
+        }
+        assert_eq!(drain(&mut queue), vec!["abcd", "abc", "ab", "a"]);
+    }
+}
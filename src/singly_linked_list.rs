@@ -130,6 +130,12 @@ impl<T> SinglyLinkedList<T> { // in Rust, the `impl` keyword is where we define
     }
 }
 
+impl<T> Default for SinglyLinkedList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)] // this syntax is called an "attribute", kind of like python decorators but not exactly
              // they can be used for a lot of things, but here it just means "this module is only compiled when running tests"
              // Rust has first class support for testing, so you can run `cargo test` to run all tests in the project
@@ -0,0 +1,1101 @@
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::ops::{Bound, RangeBounds};
+
+// nodes live in a Vec and refer to each other by index, with NIL standing in for a null link;
+// the parent links make rotations and successor lookups possible without a stack
+const NIL: usize = usize::MAX;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Color {
+    Red,
+    Black,
+}
+
+struct Node<K, V> {
+    key: K,
+    value: V,
+    color: Color,
+    parent: usize,
+    left: usize,
+    right: usize,
+}
+
+pub struct TreeMap<K, V> {
+    nodes: Vec<Node<K, V>>,
+    root: usize,
+}
+
+impl<K, V> TreeMap<K, V> {
+    pub fn new() -> Self {
+        TreeMap {
+            nodes: Vec::new(),
+            root: NIL,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn size(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.root = NIL;
+    }
+
+    pub fn first_key_value(&self) -> Option<(&K, &V)> {
+        self.entry_at(self.min_index(self.root))
+    }
+
+    pub fn last_key_value(&self) -> Option<(&K, &V)> {
+        self.entry_at(self.max_index(self.root))
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            map: self,
+            front: self.min_index(self.root),
+            back: self.max_index(self.root),
+            remaining: self.nodes.len(),
+        }
+    }
+
+    fn entry_at(&self, index: usize) -> Option<(&K, &V)> {
+        self.nodes.get(index).map(|node| (&node.key, &node.value))
+    }
+
+    fn color(&self, index: usize) -> Color {
+        // NIL leaves count as black
+        if index == NIL {
+            Color::Black
+        } else {
+            self.nodes[index].color
+        }
+    }
+
+    fn min_index(&self, mut index: usize) -> usize {
+        if index == NIL {
+            return NIL;
+        }
+        while self.nodes[index].left != NIL {
+            index = self.nodes[index].left;
+        }
+        index
+    }
+
+    fn max_index(&self, mut index: usize) -> usize {
+        if index == NIL {
+            return NIL;
+        }
+        while self.nodes[index].right != NIL {
+            index = self.nodes[index].right;
+        }
+        index
+    }
+
+    fn next_index(&self, mut index: usize) -> usize {
+        if self.nodes[index].right != NIL {
+            return self.min_index(self.nodes[index].right);
+        }
+
+        let mut parent = self.nodes[index].parent;
+        while parent != NIL && index == self.nodes[parent].right {
+            index = parent;
+            parent = self.nodes[parent].parent;
+        }
+        parent
+    }
+
+    fn prev_index(&self, mut index: usize) -> usize {
+        if self.nodes[index].left != NIL {
+            return self.max_index(self.nodes[index].left);
+        }
+
+        let mut parent = self.nodes[index].parent;
+        while parent != NIL && index == self.nodes[parent].left {
+            index = parent;
+            parent = self.nodes[parent].parent;
+        }
+        parent
+    }
+
+    // points whatever referred to `old` (its parent's child link, or the root) at `new`
+    fn replace_child(&mut self, parent: usize, old: usize, new: usize) {
+        if parent == NIL {
+            self.root = new;
+        } else if self.nodes[parent].left == old {
+            self.nodes[parent].left = new;
+        } else {
+            self.nodes[parent].right = new;
+        }
+    }
+
+    fn rotate_left(&mut self, index: usize) {
+        let pivot = self.nodes[index].right;
+        let inner = self.nodes[pivot].left;
+
+        self.nodes[index].right = inner;
+        if inner != NIL {
+            self.nodes[inner].parent = index;
+        }
+
+        let parent = self.nodes[index].parent;
+        self.nodes[pivot].parent = parent;
+        self.replace_child(parent, index, pivot);
+
+        self.nodes[pivot].left = index;
+        self.nodes[index].parent = pivot;
+    }
+
+    fn rotate_right(&mut self, index: usize) {
+        let pivot = self.nodes[index].left;
+        let inner = self.nodes[pivot].right;
+
+        self.nodes[index].left = inner;
+        if inner != NIL {
+            self.nodes[inner].parent = index;
+        }
+
+        let parent = self.nodes[index].parent;
+        self.nodes[pivot].parent = parent;
+        self.replace_child(parent, index, pivot);
+
+        self.nodes[pivot].right = index;
+        self.nodes[index].parent = pivot;
+    }
+
+    // links a new red node under `parent` (NIL for an empty tree) and restores the red-black rules
+    fn attach(&mut self, parent: usize, go_left: bool, key: K, value: V) -> usize {
+        let index = self.nodes.len();
+        self.nodes.push(Node {
+            key,
+            value,
+            color: Color::Red,
+            parent,
+            left: NIL,
+            right: NIL,
+        });
+
+        if parent == NIL {
+            self.root = index;
+        } else if go_left {
+            self.nodes[parent].left = index;
+        } else {
+            self.nodes[parent].right = index;
+        }
+
+        self.insert_fixup(index);
+        index
+    }
+
+    fn insert_fixup(&mut self, mut index: usize) {
+        while self.color(self.nodes[index].parent) == Color::Red {
+            // a red parent is never the root, so the grandparent exists
+            let parent = self.nodes[index].parent;
+            let grandparent = self.nodes[parent].parent;
+
+            if parent == self.nodes[grandparent].left {
+                let uncle = self.nodes[grandparent].right;
+                if self.color(uncle) == Color::Red {
+                    self.nodes[parent].color = Color::Black;
+                    self.nodes[uncle].color = Color::Black;
+                    self.nodes[grandparent].color = Color::Red;
+                    index = grandparent;
+                    continue;
+                }
+
+                if index == self.nodes[parent].right {
+                    index = parent;
+                    self.rotate_left(index);
+                }
+                let parent = self.nodes[index].parent;
+                self.nodes[parent].color = Color::Black;
+                self.nodes[grandparent].color = Color::Red;
+                self.rotate_right(grandparent);
+            } else {
+                let uncle = self.nodes[grandparent].left;
+                if self.color(uncle) == Color::Red {
+                    self.nodes[parent].color = Color::Black;
+                    self.nodes[uncle].color = Color::Black;
+                    self.nodes[grandparent].color = Color::Red;
+                    index = grandparent;
+                    continue;
+                }
+
+                if index == self.nodes[parent].left {
+                    index = parent;
+                    self.rotate_right(index);
+                }
+                let parent = self.nodes[index].parent;
+                self.nodes[parent].color = Color::Black;
+                self.nodes[grandparent].color = Color::Red;
+                self.rotate_left(grandparent);
+            }
+        }
+
+        let root = self.root;
+        self.nodes[root].color = Color::Black;
+    }
+
+    // unlinks the node at `index` from the tree, rebalances, and frees its slot
+    fn remove_at(&mut self, mut index: usize) -> (K, V) {
+        // a node with two children trades places with its successor, which has at most one
+        if self.nodes[index].left != NIL && self.nodes[index].right != NIL {
+            let successor = self.min_index(self.nodes[index].right);
+            self.swap_entries(index, successor);
+            index = successor;
+        }
+
+        let node = &self.nodes[index];
+        let child = if node.left != NIL { node.left } else { node.right };
+        let parent = node.parent;
+        let color = node.color;
+
+        self.replace_child(parent, index, child);
+        if child != NIL {
+            self.nodes[child].parent = parent;
+        }
+
+        if color == Color::Black {
+            self.remove_fixup(child, parent);
+        }
+
+        self.free(index)
+    }
+
+    fn remove_fixup(&mut self, mut index: usize, mut parent: usize) {
+        // `index` carries an extra black; it may be NIL, so its parent is tracked separately
+        while index != self.root && self.color(index) == Color::Black {
+            if index == self.nodes[parent].left {
+                let mut sibling = self.nodes[parent].right;
+                if self.color(sibling) == Color::Red {
+                    self.nodes[sibling].color = Color::Black;
+                    self.nodes[parent].color = Color::Red;
+                    self.rotate_left(parent);
+                    sibling = self.nodes[parent].right;
+                }
+
+                let (near, far) = (self.nodes[sibling].left, self.nodes[sibling].right);
+                if self.color(near) == Color::Black && self.color(far) == Color::Black {
+                    self.nodes[sibling].color = Color::Red;
+                    index = parent;
+                    parent = self.nodes[index].parent;
+                    continue;
+                }
+
+                if self.color(far) == Color::Black {
+                    self.nodes[near].color = Color::Black;
+                    self.nodes[sibling].color = Color::Red;
+                    self.rotate_right(sibling);
+                    sibling = self.nodes[parent].right;
+                }
+                self.nodes[sibling].color = self.nodes[parent].color;
+                self.nodes[parent].color = Color::Black;
+                let far = self.nodes[sibling].right;
+                self.nodes[far].color = Color::Black;
+                self.rotate_left(parent);
+            } else {
+                let mut sibling = self.nodes[parent].left;
+                if self.color(sibling) == Color::Red {
+                    self.nodes[sibling].color = Color::Black;
+                    self.nodes[parent].color = Color::Red;
+                    self.rotate_right(parent);
+                    sibling = self.nodes[parent].left;
+                }
+
+                let (near, far) = (self.nodes[sibling].right, self.nodes[sibling].left);
+                if self.color(near) == Color::Black && self.color(far) == Color::Black {
+                    self.nodes[sibling].color = Color::Red;
+                    index = parent;
+                    parent = self.nodes[index].parent;
+                    continue;
+                }
+
+                if self.color(far) == Color::Black {
+                    self.nodes[near].color = Color::Black;
+                    self.nodes[sibling].color = Color::Red;
+                    self.rotate_left(sibling);
+                    sibling = self.nodes[parent].left;
+                }
+                self.nodes[sibling].color = self.nodes[parent].color;
+                self.nodes[parent].color = Color::Black;
+                let far = self.nodes[sibling].left;
+                self.nodes[far].color = Color::Black;
+                self.rotate_right(parent);
+            }
+            index = self.root;
+        }
+
+        if index != NIL {
+            self.nodes[index].color = Color::Black;
+        }
+    }
+
+    fn swap_entries(&mut self, a: usize, b: usize) {
+        let (low, high) = (a.min(b), a.max(b));
+        let (head, tail) = self.nodes.split_at_mut(high);
+        std::mem::swap(&mut head[low].key, &mut tail[0].key);
+        std::mem::swap(&mut head[low].value, &mut tail[0].value);
+    }
+
+    // removes an already unlinked node from the arena; the last node moves into its slot,
+    // so the links pointing at that node are redirected
+    fn free(&mut self, index: usize) -> (K, V) {
+        let removed = self.nodes.swap_remove(index);
+
+        if index < self.nodes.len() {
+            let moved = self.nodes.len();
+            let (parent, left, right) = {
+                let node = &self.nodes[index];
+                (node.parent, node.left, node.right)
+            };
+            self.replace_child(parent, moved, index);
+            if left != NIL {
+                self.nodes[left].parent = index;
+            }
+            if right != NIL {
+                self.nodes[right].parent = index;
+            }
+        }
+
+        (removed.key, removed.value)
+    }
+}
+
+impl<K: Ord, V> TreeMap<K, V> {
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.entry(key) {
+            Entry::Occupied(mut entry) => Some(entry.insert(value)),
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+                None
+            }
+        }
+    }
+
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+        let mut parent = NIL;
+        let mut current = self.root;
+        let mut go_left = false;
+
+        while current != NIL {
+            parent = current;
+            match key.cmp(&self.nodes[current].key) {
+                Ordering::Less => {
+                    go_left = true;
+                    current = self.nodes[current].left;
+                }
+                Ordering::Greater => {
+                    go_left = false;
+                    current = self.nodes[current].right;
+                }
+                Ordering::Equal => {
+                    return Entry::Occupied(OccupiedEntry {
+                        map: self,
+                        index: current,
+                    });
+                }
+            }
+        }
+
+        Entry::Vacant(VacantEntry {
+            map: self,
+            key,
+            parent,
+            go_left,
+        })
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let index = self.find(key);
+        self.nodes.get(index).map(|node| &node.value)
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let index = self.find(key);
+        self.nodes.get_mut(index).map(|node| &mut node.value)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.find(key) != NIL
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let index = self.find(key);
+        if index == NIL {
+            return None;
+        }
+        Some(self.remove_at(index).1)
+    }
+
+    pub fn range<Q, R>(&self, range: R) -> Range<'_, K, V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let front = self.lower_bound(range.start_bound());
+        let back = self.upper_bound(range.end_bound());
+
+        // the ends start out crossed when nothing lies between the bounds
+        let is_empty = front == NIL
+            || back == NIL
+            || self.nodes[front].key.borrow() > self.nodes[back].key.borrow();
+        if is_empty {
+            return Range { map: self, front: NIL, back: NIL };
+        }
+
+        Range { map: self, front, back }
+    }
+
+    // checks every red-black rule plus the ordering and the parent links, describing the first
+    // violation found; meant for tests that want to validate the tree after each mutation
+    pub fn check_invariants(&self) -> Result<(), String> {
+        if self.root == NIL {
+            return match self.nodes.len() {
+                0 => Ok(()),
+                len => Err(format!("empty tree still holds {len} nodes")),
+            };
+        }
+
+        if self.nodes[self.root].parent != NIL {
+            return Err(String::from("root has a parent"));
+        }
+        if self.nodes[self.root].color != Color::Black {
+            return Err(String::from("root is red"));
+        }
+
+        let mut count = 0;
+        self.check_subtree(self.root, None, None, &mut count)?;
+        if count != self.nodes.len() {
+            return Err(format!("{count} nodes reachable but {} stored", self.nodes.len()));
+        }
+        Ok(())
+    }
+
+    // returns the black height of the subtree; recursion depth is the tree height
+    fn check_subtree(
+        &self,
+        index: usize,
+        lower: Option<&K>,
+        upper: Option<&K>,
+        count: &mut usize,
+    ) -> Result<usize, String> {
+        if index == NIL {
+            return Ok(1);
+        }
+
+        *count += 1;
+        let node = &self.nodes[index];
+        let below_lower = lower.is_some_and(|lower| node.key <= *lower);
+        let above_upper = upper.is_some_and(|upper| node.key >= *upper);
+        if below_lower || above_upper {
+            return Err(format!("node {index} is out of order"));
+        }
+
+        for child in [node.left, node.right] {
+            if child == NIL {
+                continue;
+            }
+            if self.nodes[child].parent != index {
+                return Err(format!("node {child} has a stale parent link"));
+            }
+            if node.color == Color::Red && self.nodes[child].color == Color::Red {
+                return Err(format!("red node {index} has a red child"));
+            }
+        }
+
+        let left = self.check_subtree(node.left, lower, Some(&node.key), count)?;
+        let right = self.check_subtree(node.right, Some(&node.key), upper, count)?;
+        if left != right {
+            return Err(format!("black heights differ below node {index}: {left} vs {right}"));
+        }
+
+        Ok(left + usize::from(node.color == Color::Black))
+    }
+
+    fn find<Q>(&self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut current = self.root;
+        while current != NIL {
+            match key.cmp(self.nodes[current].key.borrow()) {
+                Ordering::Less => current = self.nodes[current].left,
+                Ordering::Greater => current = self.nodes[current].right,
+                Ordering::Equal => return current,
+            }
+        }
+        NIL
+    }
+
+    // smallest node above the start bound
+    fn lower_bound<Q>(&self, bound: Bound<&Q>) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut best = NIL;
+        let mut current = self.root;
+        while current != NIL {
+            let key = self.nodes[current].key.borrow();
+            let above = match bound {
+                Bound::Included(start) => key >= start,
+                Bound::Excluded(start) => key > start,
+                Bound::Unbounded => true,
+            };
+            if above {
+                best = current;
+                current = self.nodes[current].left;
+            } else {
+                current = self.nodes[current].right;
+            }
+        }
+        best
+    }
+
+    // largest node below the end bound
+    fn upper_bound<Q>(&self, bound: Bound<&Q>) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut best = NIL;
+        let mut current = self.root;
+        while current != NIL {
+            let key = self.nodes[current].key.borrow();
+            let below = match bound {
+                Bound::Included(end) => key <= end,
+                Bound::Excluded(end) => key < end,
+                Bound::Unbounded => true,
+            };
+            if below {
+                best = current;
+                current = self.nodes[current].right;
+            } else {
+                current = self.nodes[current].left;
+            }
+        }
+        best
+    }
+}
+
+impl<K, V> Default for TreeMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub enum Entry<'a, K, V> {
+    Occupied(OccupiedEntry<'a, K, V>),
+    Vacant(VacantEntry<'a, K, V>),
+}
+
+pub struct OccupiedEntry<'a, K, V> {
+    map: &'a mut TreeMap<K, V>,
+    index: usize,
+}
+
+// remembers where the descent fell off the tree, so inserting doesn't search again
+pub struct VacantEntry<'a, K, V> {
+    map: &'a mut TreeMap<K, V>,
+    key: K,
+    parent: usize,
+    go_left: bool,
+}
+
+impl<'a, K, V> Entry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    pub fn or_insert_with<F>(self, default: F) -> &'a mut V
+    where
+        F: FnOnce() -> V,
+    {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    pub fn or_insert_with_key<F>(self, default: F) -> &'a mut V
+    where
+        F: FnOnce(&K) -> V,
+    {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let value = default(&entry.key);
+                entry.insert(value)
+            }
+        }
+    }
+
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    pub fn and_modify<F>(mut self, modify: F) -> Self
+    where
+        F: FnOnce(&mut V),
+    {
+        if let Entry::Occupied(entry) = &mut self {
+            modify(entry.get_mut());
+        }
+        self
+    }
+}
+
+impl<'a, K, V> OccupiedEntry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        &self.map.nodes[self.index].key
+    }
+
+    pub fn get(&self) -> &V {
+        &self.map.nodes[self.index].value
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut self.map.nodes[self.index].value
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        &mut self.map.nodes[self.index].value
+    }
+
+    pub fn insert(&mut self, value: V) -> V {
+        std::mem::replace(self.get_mut(), value)
+    }
+
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+
+    pub fn remove_entry(self) -> (K, V) {
+        self.map.remove_at(self.index)
+    }
+}
+
+impl<'a, K, V> VacantEntry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    pub fn insert(self, value: V) -> &'a mut V {
+        // rebalancing only relinks nodes, so the new node keeps the index it was given
+        let index = self.map.attach(self.parent, self.go_left, self.key, value);
+        &mut self.map.nodes[index].value
+    }
+}
+
+pub struct Iter<'a, K, V> {
+    map: &'a TreeMap<K, V>,
+    front: usize,
+    back: usize,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let index = self.front;
+        self.front = self.map.next_index(index);
+        self.remaining -= 1;
+        self.map.entry_at(index)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V> DoubleEndedIterator for Iter<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let index = self.back;
+        self.back = self.map.prev_index(index);
+        self.remaining -= 1;
+        self.map.entry_at(index)
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+pub struct Range<'a, K, V> {
+    map: &'a TreeMap<K, V>,
+    front: usize,
+    back: usize,
+}
+
+impl<'a, K, V> Iterator for Range<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front == NIL {
+            return None;
+        }
+
+        let index = self.front;
+        if index == self.back {
+            self.front = NIL;
+            self.back = NIL;
+        } else {
+            self.front = self.map.next_index(index);
+        }
+        self.map.entry_at(index)
+    }
+}
+
+impl<K, V> DoubleEndedIterator for Range<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.back == NIL {
+            return None;
+        }
+
+        let index = self.back;
+        if index == self.front {
+            self.front = NIL;
+            self.back = NIL;
+        } else {
+            self.back = self.map.prev_index(index);
+        }
+        self.map.entry_at(index)
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a TreeMap<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_map(keys: &[i32]) -> TreeMap<i32, String> {
+        let mut map = TreeMap::new();
+        for &key in keys {
+            map.insert(key, key.to_string());
+            map.check_invariants().unwrap();
+        }
+        map
+    }
+
+    fn keys(map: &TreeMap<i32, String>) -> Vec<i32> {
+        map.iter().map(|(&key, _)| key).collect()
+    }
+
+    // a deterministic shuffle so the tests exercise every fixup case without a rand dependency
+    fn shuffled(count: i32) -> Vec<i32> {
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut values: Vec<i32> = (0..count).collect();
+        for i in (1..values.len()).rev() {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            values.swap(i, (state % (i as u64 + 1)) as usize);
+        }
+        values
+    }
+
+    #[test]
+    fn test_new_is_empty() {
+        let map: TreeMap<i32, i32> = TreeMap::new();
+        assert!(map.is_empty());
+        assert_eq!(map.size(), 0);
+        assert_eq!(map.first_key_value(), None);
+        assert_eq!(map.last_key_value(), None);
+        assert!(map.check_invariants().is_ok());
+    }
+
+    #[test]
+    fn test_insert_returns_old_value() {
+        let mut map = TreeMap::new();
+        assert_eq!(map.insert(1, "one"), None);
+        assert_eq!(map.insert(2, "two"), None);
+        assert_eq!(map.insert(1, "uno"), Some("one"));
+
+        assert_eq!(map.size(), 2);
+        assert_eq!(map.get(&1), Some(&"uno"));
+        assert_eq!(map.get(&3), None);
+    }
+
+    #[test]
+    fn test_get_mut() {
+        let mut map = create_test_map(&[5, 3, 8]);
+
+        if let Some(value) = map.get_mut(&3) {
+            value.push_str("!!");
+        }
+        assert_eq!(map.get(&3), Some(&String::from("3!!")));
+        assert_eq!(map.get_mut(&4), None);
+    }
+
+    #[test]
+    fn test_borrowed_keys() {
+        let mut map = TreeMap::new();
+        map.insert(String::from("apple"), 1);
+        map.insert(String::from("pear"), 2);
+
+        assert_eq!(map.get("apple"), Some(&1));
+        assert!(map.contains_key("pear"));
+        assert_eq!(map.remove("apple"), Some(1));
+        assert!(!map.contains_key("apple"));
+    }
+
+    #[test]
+    fn test_first_and_last() {
+        let mut map = create_test_map(&[50, 30, 70, 20, 80]);
+        assert_eq!(map.first_key_value(), Some((&20, &String::from("20"))));
+        assert_eq!(map.last_key_value(), Some((&80, &String::from("80"))));
+
+        map.remove(&20);
+        map.remove(&80);
+        assert_eq!(map.first_key_value().map(|(&key, _)| key), Some(30));
+        assert_eq!(map.last_key_value().map(|(&key, _)| key), Some(70));
+    }
+
+    #[test]
+    fn test_invariants_hold_after_every_insert() {
+        let mut map = TreeMap::new();
+        for i in 0..1_000 {
+            map.insert(i, i);
+            map.check_invariants().unwrap();
+        }
+        for key in shuffled(1_000) {
+            map.insert(key + 1_000, key);
+            map.check_invariants().unwrap();
+        }
+        assert_eq!(map.size(), 2_000);
+    }
+
+    #[test]
+    fn test_invariants_hold_after_every_remove() {
+        let order = shuffled(1_000);
+        let mut map = TreeMap::new();
+        for &key in &order {
+            map.insert(key, key * 10);
+        }
+
+        for (removed, key) in shuffled(1_000).into_iter().enumerate() {
+            assert_eq!(map.remove(&key), Some(key * 10));
+            assert_eq!(map.remove(&key), None);
+            map.check_invariants().unwrap();
+            assert_eq!(map.size(), 999 - removed);
+        }
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn test_interleaved_mutations() {
+        let mut map = TreeMap::new();
+        for (step, key) in shuffled(2_000).into_iter().enumerate() {
+            let key = key % 300;
+            if step % 3 == 0 {
+                map.remove(&key);
+            } else {
+                map.insert(key, step);
+            }
+            map.check_invariants().unwrap();
+        }
+
+        let keys: Vec<_> = map.iter().map(|(&key, _)| key).collect();
+        assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_iter() {
+        let map = create_test_map(&[50, 30, 70, 20, 40, 60, 80]);
+
+        assert_eq!(keys(&map), vec![20, 30, 40, 50, 60, 70, 80]);
+        assert_eq!(map.iter().rev().map(|(&key, _)| key).collect::<Vec<_>>(), vec![80, 70, 60, 50, 40, 30, 20]);
+
+        let mut iter = map.iter();
+        assert_eq!(iter.len(), 7);
+        assert_eq!(iter.next().map(|(&key, _)| key), Some(20));
+        assert_eq!(iter.next_back().map(|(&key, _)| key), Some(80));
+        assert_eq!(iter.map(|(&key, _)| key).collect::<Vec<_>>(), vec![30, 40, 50, 60, 70]);
+    }
+
+    #[test]
+    fn test_range() {
+        let map = create_test_map(&[50, 30, 70, 20, 40, 60, 80]);
+        let range_keys = |range: Range<'_, i32, String>| range.map(|(&key, _)| key).collect::<Vec<_>>();
+
+        assert_eq!(range_keys(map.range(30..70)), vec![30, 40, 50, 60]);
+        assert_eq!(range_keys(map.range(35..=70)), vec![40, 50, 60, 70]);
+        assert_eq!(range_keys(map.range(..40)), vec![20, 30]);
+        assert_eq!(range_keys(map.range(75..)), vec![80]);
+        assert_eq!(range_keys(map.range(41..50)), Vec::<i32>::new());
+        assert_eq!(range_keys(map.range(90..)), Vec::<i32>::new());
+        assert_eq!(map.range(25..75).rev().map(|(&key, _)| key).collect::<Vec<_>>(), vec![70, 60, 50, 40, 30]);
+
+        let mut range = map.range(25..75);
+        assert_eq!(range.next().map(|(&key, _)| key), Some(30));
+        assert_eq!(range.next_back().map(|(&key, _)| key), Some(70));
+        assert_eq!(range.next().map(|(&key, _)| key), Some(40));
+        assert_eq!(range.next_back().map(|(&key, _)| key), Some(60));
+        assert_eq!(range.next().map(|(&key, _)| key), Some(50));
+        assert_eq!(range.next_back(), None);
+        assert_eq!(range.next(), None);
+    }
+
+    #[test]
+    fn test_entry_or_insert() {
+        let mut counts = TreeMap::new();
+        for word in ["b", "a", "c", "a", "b", "a"] {
+            *counts.entry(word).or_insert(0) += 1;
+        }
+
+        assert_eq!(counts.get("a"), Some(&3));
+        assert_eq!(counts.get("b"), Some(&2));
+        assert_eq!(counts.get("c"), Some(&1));
+        assert_eq!(counts.size(), 3);
+        counts.check_invariants().unwrap();
+    }
+
+    #[test]
+    fn test_entry_or_insert_with_and_default() {
+        let mut map: TreeMap<i32, Vec<i32>> = TreeMap::new();
+        map.entry(1).or_insert_with(|| vec![10]).push(11);
+        map.entry(1).or_insert_with(|| unreachable!()).push(12);
+        map.entry(2).or_default().push(20);
+        map.entry(3).or_insert_with_key(|&key| vec![key * 10]);
+
+        assert_eq!(map.get(&1), Some(&vec![10, 11, 12]));
+        assert_eq!(map.get(&2), Some(&vec![20]));
+        assert_eq!(map.get(&3), Some(&vec![30]));
+    }
+
+    #[test]
+    fn test_entry_and_modify() {
+        let mut map = TreeMap::new();
+        map.entry("x").and_modify(|value| *value += 1).or_insert(1);
+        map.entry("x").and_modify(|value| *value += 1).or_insert(1);
+        map.entry("y").and_modify(|value| *value += 1).or_insert(1);
+
+        assert_eq!(map.get("x"), Some(&2));
+        assert_eq!(map.get("y"), Some(&1));
+    }
+
+    #[test]
+    fn test_entry_occupied_and_vacant() {
+        let mut map = create_test_map(&[50, 30, 70]);
+
+        match map.entry(30) {
+            Entry::Occupied(mut entry) => {
+                assert_eq!(entry.key(), &30);
+                assert_eq!(entry.insert(String::from("thirty")), "30");
+                assert_eq!(entry.get(), "thirty");
+            }
+            Entry::Vacant(_) => panic!("30 should be occupied"),
+        }
+
+        match map.entry(40) {
+            Entry::Vacant(entry) => {
+                assert_eq!(entry.key(), &40);
+                entry.insert(String::from("forty"));
+            }
+            Entry::Occupied(_) => panic!("40 should be vacant"),
+        }
+
+        assert_eq!(map.entry(60).key(), &60);
+        assert_eq!(map.size(), 4);
+        assert_eq!(keys(&map), vec![30, 40, 50, 70]);
+        map.check_invariants().unwrap();
+    }
+
+    #[test]
+    fn test_entry_remove_entry() {
+        let mut map = create_test_map(&[50, 30, 70, 20, 40]);
+
+        if let Entry::Occupied(entry) = map.entry(30) {
+            assert_eq!(entry.remove_entry(), (30, String::from("30")));
+        }
+        if let Entry::Occupied(entry) = map.entry(50) {
+            assert_eq!(entry.remove(), "50");
+        }
+
+        assert_eq!(keys(&map), vec![20, 40, 70]);
+        map.check_invariants().unwrap();
+    }
+
+    #[test]
+    fn test_entry_keeps_invariants() {
+        let mut map = TreeMap::new();
+        for (step, key) in shuffled(1_000).into_iter().enumerate() {
+            match map.entry(key % 200) {
+                Entry::Occupied(entry) if step % 2 == 0 => {
+                    entry.remove_entry();
+                }
+                entry => {
+                    entry.and_modify(|count| *count += 1).or_insert(1);
+                }
+            }
+            map.check_invariants().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut map = create_test_map(&[1, 2, 3]);
+        map.clear();
+
+        assert!(map.is_empty());
+        assert_eq!(map.get(&1), None);
+        map.insert(4, String::from("4"));
+        assert_eq!(map.size(), 1);
+        map.check_invariants().unwrap();
+    }
+}